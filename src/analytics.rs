@@ -0,0 +1,522 @@
+use std::{collections::BTreeMap, fmt};
+
+use crate::{
+    models::Invoice,
+    money::{Currency, Money},
+    resource::{Resource, PAGE_SIZE},
+    timestamp::{civil_from_days, days_from_civil, Timestamp},
+    SellAppClient,
+};
+
+/// Length of the time buckets revenue is grouped by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    /// Grouped as **"2024-08-15"**.
+    Day,
+    /// Grouped by ISO 8601 week, as **"2024-W33"**.
+    Week,
+    /// Grouped as **"2024-08"**.
+    Month,
+}
+
+/// What revenue is grouped by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dimension {
+    /// The creation date of the invoice.
+    Period(Period),
+    /// The product ID of each bought variant.
+    Product,
+    /// The product and variant ID of each bought variant, as **"product_id:variant_id"**.
+    Variant,
+    /// The payment gateway, e.g. **"STRIPE"**.
+    Gateway,
+    /// The country code of the customer.
+    Country,
+    /// The coupon ID, or an empty string for invoices without a coupon.
+    Coupon,
+}
+
+/// Group of a revenue row. Amounts in different currencies are never added together.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RevenueKey {
+    pub group: String,
+    pub currency: Currency,
+}
+
+/// Revenue of a single group.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Revenue {
+    /// Revenue before coupon discounts.
+    pub gross: Money,
+    /// Revenue actually paid by customers.
+    pub net: Money,
+    /// Amount of invoices that contributed to this group, or invoice lines for the ``Product`` and ``Variant`` dimensions.
+    pub invoices: usize,
+}
+
+/// Why revenue was left out of a ``RevenueAnalytics``.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The invoice is completed, but has no total.
+    MissingTotal,
+    /// The bought variant has no price, so its share of the invoice is unknown.
+    MissingPrice,
+    /// The variant price is in another currency than the invoice total.
+    CurrencyMismatch,
+    /// The amount doesn't fit in ``Money``, or adding it would overflow the revenue of its group.
+    Overflow,
+}
+
+/// An invoice, or a single line of one, whose revenue couldn't be counted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedRevenue {
+    pub invoice_id: String,
+    /// The bought variant as **"product_id:variant_id"**, if only that line of the invoice was skipped.
+    pub line: Option<String>,
+    pub reason: SkipReason,
+}
+
+/// Revenue of completed invoices, grouped by a single ``Dimension``.
+///
+/// Invoices can be added one by one with ``add``, which makes it usable both with invoices you already have
+/// (see ``from_invoices``) and with ``SellAppClient::revenue_analytics``, which pages through all invoices.
+///
+/// For the ``Product`` and ``Variant`` dimensions, gross revenue is the variant price times the quantity,
+/// and net revenue is the gross revenue scaled by the discount of the whole invoice.
+///
+/// Revenue that can't be counted exactly isn't guessed, but left out and listed in ``skipped``.
+#[derive(Debug, Clone)]
+pub struct RevenueAnalytics {
+    dimension: Dimension,
+    rows: BTreeMap<RevenueKey, Revenue>,
+    skipped: Vec<SkippedRevenue>,
+}
+
+impl RevenueAnalytics {
+    pub fn new(dimension: Dimension) -> Self {
+        return RevenueAnalytics {
+            dimension,
+            rows: BTreeMap::new(),
+            skipped: Vec::new(),
+        };
+    }
+
+    /// Compute the revenue of ``invoices``.
+    pub fn from_invoices(dimension: Dimension, invoices: &[Invoice]) -> Self {
+        let mut analytics = Self::new(dimension);
+        for invoice in invoices {
+            analytics.add(invoice);
+        }
+        return analytics;
+    }
+
+    pub fn dimension(&self) -> Dimension {
+        return self.dimension;
+    }
+
+    /// The computed revenue, sorted by group and currency.
+    pub fn rows(&self) -> &BTreeMap<RevenueKey, Revenue> {
+        return &self.rows;
+    }
+
+    /// The invoices and invoice lines left out of ``rows``, in the order they were added.
+    pub fn skipped(&self) -> &[SkippedRevenue] {
+        return &self.skipped;
+    }
+
+    /// Add an invoice to the revenue. Invoices that are not completed are ignored.
+    pub fn add(&mut self, invoice: &Invoice) {
+        if invoice.status.as_deref() != Some("COMPLETED") {
+            return;
+        }
+        let net = match invoice.payment.total {
+            Some(total) => total,
+            None => return self.skip(invoice, None, SkipReason::MissingTotal),
+        };
+        let gross = invoice
+            .payment
+            .subtotal
+            .filter(|subtotal| subtotal.currency == net.currency)
+            .unwrap_or(net);
+
+        let group = match self.dimension {
+            Dimension::Period(period) => period_key(period, invoice.created_at),
+            Dimension::Gateway => invoice.payment.gateway.kind.clone().unwrap_or_default(),
+            Dimension::Country => invoice
+                .customer_information
+                .country
+                .clone()
+                .unwrap_or_default(),
+            Dimension::Coupon => invoice.coupon_id.clone().unwrap_or_default(),
+            Dimension::Product | Dimension::Variant => {
+                for product in &invoice.products {
+                    for variant in &product.variants {
+                        let line = format!("{}:{}", product.id, variant.id);
+                        let price = match variant.price {
+                            Some(price) if price.currency != net.currency => {
+                                self.skip(invoice, Some(line), SkipReason::CurrencyMismatch);
+                                continue;
+                            }
+                            Some(price) => price,
+                            None => {
+                                self.skip(invoice, Some(line), SkipReason::MissingPrice);
+                                continue;
+                            }
+                        };
+                        let amounts =
+                            price
+                                .checked_mul(variant.quantity as i64)
+                                .and_then(|line_gross| {
+                                    let line_net = match gross.is_zero() {
+                                        true => line_gross,
+                                        false => line_gross
+                                            .checked_scale(net.amount_minor, gross.amount_minor)?,
+                                    };
+                                    return Ok((line_gross, line_net));
+                                });
+                        let group = match self.dimension {
+                            Dimension::Product => product.id.clone(),
+                            _ => line.clone(),
+                        };
+                        let recorded = match amounts {
+                            Ok((line_gross, line_net)) => self.record(group, line_gross, line_net),
+                            Err(_) => false,
+                        };
+                        if !recorded {
+                            self.skip(invoice, Some(line), SkipReason::Overflow);
+                        }
+                    }
+                }
+                return;
+            }
+        };
+        if !self.record(group, gross, net) {
+            self.skip(invoice, None, SkipReason::Overflow);
+        }
+    }
+
+    /// Add amounts in the same currency to a group. Returns ``false`` without changing the group if its
+    /// revenue would overflow.
+    fn record(&mut self, group: String, gross: Money, net: Money) -> bool {
+        let currency = net.currency;
+        let key = RevenueKey { group, currency };
+        let revenue = match self.rows.get(&key) {
+            Some(revenue) => revenue.clone(),
+            None => Revenue {
+                gross: Money::zero(currency),
+                net: Money::zero(currency),
+                invoices: 0,
+            },
+        };
+        let (gross, net) = match (
+            revenue.gross.checked_add(gross),
+            revenue.net.checked_add(net),
+        ) {
+            (Ok(gross), Ok(net)) => (gross, net),
+            _ => return false,
+        };
+        let invoices = revenue.invoices + 1;
+        self.rows.insert(
+            key,
+            Revenue {
+                gross,
+                net,
+                invoices,
+            },
+        );
+        return true;
+    }
+
+    fn skip(&mut self, invoice: &Invoice, line: Option<String>, reason: SkipReason) {
+        self.skipped.push(SkippedRevenue {
+            invoice_id: invoice.id.clone(),
+            line,
+            reason,
+        });
+    }
+}
+
+/// Error returned while computing revenue from the API.
+#[derive(Debug)]
+pub enum AnalyticsError {
+    /// Fetching invoices from the API failed.
+    Http(crate::Error),
+    /// Parsing an invoice failed.
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for AnalyticsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            AnalyticsError::Http(error) => write!(f, "failed to fetch invoices: {}", error),
+            AnalyticsError::Json(error) => write!(f, "failed to parse invoice: {}", error),
+        };
+    }
+}
+
+impl std::error::Error for AnalyticsError {}
+
+impl From<crate::Error> for AnalyticsError {
+    fn from(error: crate::Error) -> Self {
+        return AnalyticsError::Http(error);
+    }
+}
+
+impl From<reqwest::Error> for AnalyticsError {
+    fn from(error: reqwest::Error) -> Self {
+        return AnalyticsError::Http(error.into());
+    }
+}
+
+impl From<serde_json::Error> for AnalyticsError {
+    fn from(error: serde_json::Error) -> Self {
+        return AnalyticsError::Json(error);
+    }
+}
+
+impl SellAppClient {
+    /// Compute the revenue of all invoices for every dimension in ``dimensions``, in the same order.
+    ///
+    /// Invoices are fetched one page at a time and are not kept in memory.
+    ///
+    /// ``extra_params``: Optional attributes to append to the invoice list URL after the paging attributes, e.g. a date
+    /// range built with ``ListQuery::to_extra_params``, so only the matching invoices are fetched.
+    ///
+    /// ```no_run
+    /// use sellapp::{
+    ///     analytics::{Dimension, Period},
+    ///     resource::ListQuery,
+    ///     timestamp::Timestamp,
+    /// };
+    ///
+    /// # async fn run() -> Result<(), sellapp::analytics::AnalyticsError> {
+    /// let sellapp_api = sellapp::init("your_api_key", "");
+    /// let since = Timestamp::parse("2024-01-01").unwrap();
+    /// let extra_params = ListQuery::new().created_from(since).to_extra_params();
+    /// let reports = sellapp_api
+    ///     .revenue_analytics(&[Dimension::Period(Period::Month), Dimension::Gateway], &extra_params)
+    ///     .await?;
+    ///
+    /// for (key, revenue) in reports[0].rows() {
+    ///     println!("{}: {}", key.group, revenue.net);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn revenue_analytics(
+        &self,
+        dimensions: &[Dimension],
+        extra_params: &str,
+    ) -> Result<Vec<RevenueAnalytics>, AnalyticsError> {
+        let mut reports: Vec<RevenueAnalytics> = dimensions
+            .iter()
+            .map(|dimension| RevenueAnalytics::new(*dimension))
+            .collect();
+
+        for page in 1.. {
+            let items = self
+                .list_page(Resource::Invoices, page, extra_params)
+                .await?;
+            let last_page = items.len() < PAGE_SIZE;
+            for item in items {
+                let invoice: Invoice = serde_json::from_value(item)?;
+                for report in reports.iter_mut() {
+                    report.add(&invoice);
+                }
+            }
+            if last_page {
+                break;
+            }
+        }
+        return Ok(reports);
+    }
+}
+
+/// Get the time bucket of a creation timestamp.
+fn period_key(period: Period, created_at: Option<Timestamp>) -> String {
+    let (year, month, day) = match created_at {
+        Some(created_at) => created_at.date(),
+        None => return String::new(),
+    };
+
+    return match period {
+        Period::Day => format!("{:04}-{:02}-{:02}", year, month, day),
+        Period::Month => format!("{:04}-{:02}", year, month),
+        Period::Week => {
+            let (week_year, week) = iso_week(year, month as i64, day as i64);
+            format!("{:04}-W{:02}", week_year, week)
+        }
+    };
+}
+
+/// Get the ISO 8601 week-numbering year and week of a date.
+fn iso_week(year: i64, month: i64, day: i64) -> (i64, i64) {
+    let days = days_from_civil(year, month, day);
+    // 1970-01-01 was a Thursday, weekdays are numbered 1 (Monday) to 7 (Sunday).
+    let weekday = (days + 3).rem_euclid(7) + 1;
+    // The week belongs to the year its Thursday is in.
+    let thursday = days - weekday + 4;
+    let week_year = civil_from_days(thursday).0;
+    let week = (thursday - days_from_civil(week_year, 1, 1)) / 7 + 1;
+    return (week_year, week);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{InvoiceProduct, InvoiceVariant, Payment};
+
+    fn usd(amount_minor: i64) -> Money {
+        return Money::new(amount_minor, Currency::new("USD").unwrap());
+    }
+
+    fn invoice(id: &str, subtotal: i64, total: i64) -> Invoice {
+        return Invoice {
+            id: id.to_string(),
+            status: Some("COMPLETED".to_string()),
+            payment: Payment {
+                subtotal: Some(usd(subtotal)),
+                total: Some(usd(total)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+    }
+
+    fn variant(id: &str, price: Option<Money>, quantity: u32) -> InvoiceVariant {
+        return InvoiceVariant {
+            id: id.to_string(),
+            price,
+            quantity,
+            ..Default::default()
+        };
+    }
+
+    fn key(group: &str) -> RevenueKey {
+        return RevenueKey {
+            group: group.to_string(),
+            currency: Currency::new("USD").unwrap(),
+        };
+    }
+
+    fn week(date: &str) -> String {
+        return period_key(Period::Week, Some(Timestamp::parse(date).unwrap()));
+    }
+
+    #[test]
+    fn groups_by_iso_week() {
+        assert_eq!(week("2026-12-28"), "2026-W53");
+        assert_eq!(week("2026-12-31"), "2026-W53");
+        assert_eq!(week("2027-01-01"), "2026-W53");
+        assert_eq!(week("2027-01-03T23:59:59Z"), "2026-W53");
+        assert_eq!(week("2027-01-04"), "2027-W01");
+        assert_eq!(week("2024-12-30"), "2025-W01");
+        assert_eq!(week("2021-01-03"), "2020-W53");
+        assert_eq!(week("2024-08-15"), "2024-W33");
+    }
+
+    #[test]
+    fn groups_by_day_and_month_in_utc() {
+        let created_at = Some(Timestamp::parse("2026-12-31T23:30:00-01:00").unwrap());
+        assert_eq!(period_key(Period::Day, created_at), "2027-01-01");
+        assert_eq!(period_key(Period::Month, created_at), "2027-01");
+        assert_eq!(period_key(Period::Month, None), "");
+    }
+
+    #[test]
+    fn allocates_net_revenue_to_lines() {
+        // 20% off 30.00: one unit of variant 10 for 10.00, two units of variant 11 for 10.00 each.
+        let mut invoice = invoice("1", 3000, 2400);
+        invoice.products = vec![InvoiceProduct {
+            id: "5".to_string(),
+            variants: vec![
+                variant("10", Some(usd(1000)), 1),
+                variant("11", Some(usd(1000)), 2),
+            ],
+            ..Default::default()
+        }];
+
+        let variants = RevenueAnalytics::from_invoices(Dimension::Variant, &[invoice.clone()]);
+        let rows = variants.rows();
+        assert_eq!(rows[&key("5:10")].gross, usd(1000));
+        assert_eq!(rows[&key("5:10")].net, usd(800));
+        assert_eq!(rows[&key("5:11")].gross, usd(2000));
+        assert_eq!(rows[&key("5:11")].net, usd(1600));
+
+        let products = RevenueAnalytics::from_invoices(Dimension::Product, &[invoice]);
+        let revenue = &products.rows()[&key("5")];
+        assert_eq!(
+            (revenue.gross, revenue.net, revenue.invoices),
+            (usd(3000), usd(2400), 2)
+        );
+        assert!(products.skipped().is_empty());
+    }
+
+    #[test]
+    fn reports_lines_that_cant_be_counted() {
+        let mut invoice = invoice("1", 2000, 2000);
+        invoice.products = vec![InvoiceProduct {
+            id: "5".to_string(),
+            variants: vec![
+                variant("10", Some(usd(1000)), 1),
+                variant("11", None, 1),
+                variant(
+                    "12",
+                    Some(Money::new(1000, Currency::new("EUR").unwrap())),
+                    1,
+                ),
+                variant("13", Some(usd(i64::MAX)), 2),
+            ],
+            ..Default::default()
+        }];
+
+        let analytics = RevenueAnalytics::from_invoices(Dimension::Variant, &[invoice]);
+        assert_eq!(analytics.rows().len(), 1);
+        let skipped: Vec<(Option<&str>, SkipReason)> = analytics
+            .skipped()
+            .iter()
+            .map(|skipped| (skipped.line.as_deref(), skipped.reason))
+            .collect();
+        assert_eq!(
+            skipped,
+            vec![
+                (Some("5:11"), SkipReason::MissingPrice),
+                (Some("5:12"), SkipReason::CurrencyMismatch),
+                (Some("5:13"), SkipReason::Overflow),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_invoices_that_cant_be_counted() {
+        let mut no_total = invoice("2", 1000, 1000);
+        no_total.payment.total = None;
+        let mut pending = invoice("3", 1000, 1000);
+        pending.status = Some("PENDING".to_string());
+        let invoices = [
+            invoice("1", i64::MAX, i64::MAX),
+            no_total,
+            pending,
+            invoice("4", 1, 1),
+        ];
+
+        let analytics = RevenueAnalytics::from_invoices(Dimension::Gateway, &invoices);
+        let revenue = &analytics.rows()[&key("")];
+        assert_eq!((revenue.net, revenue.invoices), (usd(i64::MAX), 1));
+        assert_eq!(
+            analytics.skipped(),
+            &[
+                SkippedRevenue {
+                    invoice_id: "2".to_string(),
+                    line: None,
+                    reason: SkipReason::MissingTotal,
+                },
+                SkippedRevenue {
+                    invoice_id: "4".to_string(),
+                    line: None,
+                    reason: SkipReason::Overflow,
+                },
+            ]
+        );
+    }
+}
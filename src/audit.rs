@@ -0,0 +1,261 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use reqwest::StatusCode;
+
+use crate::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    middleware::RequestContext,
+    rt::{SystemTime, UNIX_EPOCH},
+    util::id_string,
+};
+
+/// Attributes that hold customer data, replaced by ``REDACTED`` when PII redaction is enabled.
+pub const PII_FIELDS: [&str; 8] = [
+    "customer_information",
+    "customer_email",
+    "email",
+    "ip",
+    "ip_address",
+    "user_agent",
+    "discord_id",
+    "phone",
+];
+
+/// Value that redacted data is replaced with.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// A mutating call recorded by the audit log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// When the call was made, in milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    /// Label of the person or system that made the call, see ``SellAppClient::with_operator``.
+    pub operator: Option<String>,
+    /// Name of the client method that issued the call, e.g. **"invoices_mark_voided"**.
+    pub operation: String,
+    pub method: String,
+    /// Path of the request, including any URL parameters, e.g. **"v2/invoices/12/void"**.
+    pub path: String,
+    /// ID of the affected resource, taken from the response or the request path.
+    pub resource_id: Option<String>,
+    /// Body of the request, parsed as JSON if possible.
+    pub request: Option<Value>,
+    /// HTTP status of the response, or ``None`` if no response was received.
+    pub status: Option<u16>,
+    /// Resource returned by the API, parsed as JSON if possible.
+    pub response: Option<Value>,
+    /// Why the call failed without a response.
+    pub error: Option<String>,
+}
+
+/// Destination of audit records.
+pub trait AuditSink: Send + Sync {
+    fn write(&self, record: &AuditRecord) -> io::Result<()>;
+}
+
+impl<T: AuditSink + ?Sized> AuditSink for Arc<T> {
+    fn write(&self, record: &AuditRecord) -> io::Result<()> {
+        return (**self).write(record);
+    }
+}
+
+/// Audit sink that appends every record as a line of JSON to a file.
+pub struct JsonlAuditSink {
+    file: Mutex<File>,
+}
+
+impl JsonlAuditSink {
+    /// Open the file for appending, creating it if it doesn't exist.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        return Ok(JsonlAuditSink {
+            file: Mutex::new(file),
+        });
+    }
+}
+
+impl AuditSink for JsonlAuditSink {
+    fn write(&self, record: &AuditRecord) -> io::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        let mut file = self.file.lock().unwrap();
+        file.write_all(&line)?;
+        return file.flush();
+    }
+}
+
+/// Audit sink that keeps the records in memory. Wrap it in an ``Arc`` to read the records while the client uses it.
+#[derive(Default)]
+pub struct MemoryAuditSink {
+    records: Mutex<Vec<AuditRecord>>,
+}
+
+impl MemoryAuditSink {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Get the records written so far, oldest first.
+    pub fn records(&self) -> Vec<AuditRecord> {
+        return self.records.lock().unwrap().clone();
+    }
+}
+
+impl AuditSink for MemoryAuditSink {
+    fn write(&self, record: &AuditRecord) -> io::Result<()> {
+        self.records.lock().unwrap().push(record.clone());
+        return Ok(());
+    }
+}
+
+/// Records every mutating call of a client, see ``SellAppClient::with_audit``.
+///
+/// GET calls and calls blocked by a middleware (e.g. in dry-run mode) are not recorded.
+pub struct AuditLog {
+    sink: Box<dyn AuditSink>,
+    redact_api_key: bool,
+    redact_pii: bool,
+    failed_writes: AtomicUsize,
+}
+
+impl AuditLog {
+    pub fn new(sink: impl AuditSink + 'static) -> Self {
+        return AuditLog {
+            sink: Box::new(sink),
+            redact_api_key: false,
+            redact_pii: false,
+            failed_writes: AtomicUsize::new(0),
+        };
+    }
+
+    /// Replace any occurrence of the API key in the records by ``REDACTED``.
+    pub fn with_redact_api_key(mut self, redact: bool) -> Self {
+        self.redact_api_key = redact;
+        return self;
+    }
+
+    /// Replace the customer data attributes listed in ``PII_FIELDS`` by ``REDACTED``.
+    pub fn with_redact_pii(mut self, redact: bool) -> Self {
+        self.redact_pii = redact;
+        return self;
+    }
+
+    /// Amount of records that could not be written to the sink. Failing to write a record never fails the call.
+    pub fn failed_writes(&self) -> usize {
+        return self.failed_writes.load(Ordering::Relaxed);
+    }
+
+    pub(crate) fn write(&self, mut record: AuditRecord, api_key: &str) {
+        if self.redact_pii {
+            for value in [&mut record.request, &mut record.response]
+                .into_iter()
+                .flatten()
+            {
+                redact_pii(value);
+            }
+        }
+        if self.redact_api_key && !api_key.is_empty() {
+            record = match serde_json::to_string(&record) {
+                Ok(json) => {
+                    serde_json::from_str(&json.replace(api_key, REDACTED)).unwrap_or(record)
+                }
+                Err(_) => record,
+            };
+        }
+        if self.sink.write(&record).is_err() {
+            self.failed_writes.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Build the record of a call, before redaction.
+pub(crate) fn audit_record(
+    ctx: &RequestContext,
+    operator: Option<&str>,
+    request: Option<&[u8]>,
+    outcome: Result<(StatusCode, &[u8]), &Error>,
+) -> AuditRecord {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis() as u64);
+    let mut path = ctx.url.path().trim_start_matches("/api/").to_string();
+    if let Some(query) = ctx.url.query() {
+        path = format!("{}?{}", path, query);
+    }
+
+    let (status, mut response, error) = match outcome {
+        Ok((status, body)) => (Some(status.as_u16()), parse_body(body), None),
+        Err(error) => (None, None, Some(error.to_string())),
+    };
+    if let Some(Value::Object(body)) = &mut response {
+        if let Some(data) = body.remove("data") {
+            response = Some(data);
+        }
+    }
+    let resource_id = response
+        .as_ref()
+        .and_then(|data| id_string(&data["id"]))
+        .or_else(|| path_id(&path));
+
+    return AuditRecord {
+        timestamp,
+        operator: operator.map(str::to_string),
+        operation: ctx.operation.to_string(),
+        method: ctx.method.to_string(),
+        path,
+        resource_id,
+        request: request.and_then(parse_body),
+        status,
+        response,
+        error,
+    };
+}
+
+fn parse_body(body: &[u8]) -> Option<Value> {
+    if body.is_empty() {
+        return None;
+    }
+    return Some(
+        serde_json::from_slice(body)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(body).into_owned())),
+    );
+}
+
+/// Get the innermost resource ID of a path like **"v2/products/12/variants/34"**.
+fn path_id(path: &str) -> Option<String> {
+    let path = path.split('?').next().unwrap_or_default();
+    return path
+        .split('/')
+        .skip(2)
+        .step_by(2)
+        .filter(|segment| segment.chars().any(|c| c.is_ascii_digit()))
+        .last()
+        .map(str::to_string);
+}
+
+fn redact_pii(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if PII_FIELDS.contains(&key.as_str()) {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact_pii(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_pii),
+        _ => {}
+    }
+}
@@ -0,0 +1,442 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, fs, io,
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    resource::Resource,
+    util::{check_response, creatable, id_string, resource_id},
+    SellAppClient,
+};
+
+/// Version of the backup format written by this version of the crate.
+pub const BACKUP_VERSION: u32 = 1;
+
+/// Snapshot of every resource in a store, created with ``SellAppClient::backup``.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Backup {
+    pub version: u32,
+    /// Every item of each resource type, by ``Resource::name``.
+    pub resources: BTreeMap<String, Vec<Value>>,
+    /// Variants of each product, by product ID.
+    pub variants: BTreeMap<String, Vec<Value>>,
+    /// IDs of the products in each group, by group ID.
+    pub group_products: BTreeMap<String, Vec<String>>,
+}
+
+impl Backup {
+    /// Write the backup to a JSON file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), BackupError> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        return Ok(());
+    }
+
+    /// Read a backup from a JSON file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BackupError> {
+        let backup: Backup = serde_json::from_slice(&fs::read(path)?)?;
+        if backup.version > BACKUP_VERSION {
+            return Err(BackupError::UnsupportedVersion(backup.version));
+        }
+        return Ok(backup);
+    }
+
+    fn items(&self, resource: Resource) -> &[Value] {
+        return self
+            .resources
+            .get(resource.name())
+            .map_or(&[], Vec::as_slice);
+    }
+}
+
+/// An item of a backup that was not restored.
+#[derive(Debug, Clone)]
+pub struct SkippedItem {
+    /// Resource type of the item, e.g. **"invoices"**.
+    pub resource: String,
+    /// ID of the item in the backup.
+    pub id: String,
+    pub reason: String,
+}
+
+/// Outcome of ``SellAppClient::restore``.
+#[derive(Debug, Clone, Default)]
+pub struct RestoreReport {
+    /// New IDs of the restored items, by resource type and ID in the backup.
+    pub ids: HashMap<(String, String), String>,
+    pub skipped: Vec<SkippedItem>,
+}
+
+impl RestoreReport {
+    /// Get the new ID of a restored item.
+    pub fn new_id(&self, resource: &str, old_id: &str) -> Option<&String> {
+        return self.ids.get(&(resource.to_string(), old_id.to_string()));
+    }
+
+    fn skip(&mut self, resource: &str, id: String, reason: String) {
+        self.skipped.push(SkippedItem {
+            resource: resource.to_string(),
+            id,
+            reason,
+        });
+    }
+}
+
+/// Error returned while creating or reading a backup.
+#[derive(Debug)]
+pub enum BackupError {
+    /// Fetching data from the API failed.
+    Http(crate::Error),
+    /// Reading or writing the backup file failed.
+    Io(io::Error),
+    /// The backup file is not valid JSON.
+    Json(serde_json::Error),
+    /// The backup was written by a newer version of the crate, with the contained backup version.
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            BackupError::Http(error) => write!(f, "failed to fetch data: {}", error),
+            BackupError::Io(error) => write!(f, "failed to access backup: {}", error),
+            BackupError::Json(error) => write!(f, "invalid backup: {}", error),
+            BackupError::UnsupportedVersion(version) => write!(
+                f,
+                "backup version {} is newer than the supported version {}",
+                version, BACKUP_VERSION
+            ),
+        };
+    }
+}
+
+impl std::error::Error for BackupError {}
+
+impl From<crate::Error> for BackupError {
+    fn from(error: crate::Error) -> Self {
+        return BackupError::Http(error);
+    }
+}
+
+impl From<reqwest::Error> for BackupError {
+    fn from(error: reqwest::Error) -> Self {
+        return BackupError::Http(error.into());
+    }
+}
+
+impl From<io::Error> for BackupError {
+    fn from(error: io::Error) -> Self {
+        return BackupError::Io(error);
+    }
+}
+
+impl From<serde_json::Error> for BackupError {
+    fn from(error: serde_json::Error) -> Self {
+        return BackupError::Json(error);
+    }
+}
+
+impl SellAppClient {
+    /// Fetch every resource of the store, including product variants and group memberships.
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), sellapp::backup::BackupError> {
+    /// let sellapp_api = sellapp::init("your_api_key", "");
+    /// sellapp_api.backup().await?.save("backup.json")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn backup(&self) -> Result<Backup, BackupError> {
+        let client = self.uncached();
+        let mut backup = Backup {
+            version: BACKUP_VERSION,
+            ..Default::default()
+        };
+
+        for resource in Resource::ALL {
+            let items = client.list_all_pages(resource, "").await?;
+            backup.resources.insert(resource.name().to_string(), items);
+        }
+        for product in backup.items(Resource::Products).to_vec() {
+            let product_id = resource_id(&product);
+            let variants = client.variants_list_all_pages(product_id.clone()).await?;
+            backup.variants.insert(product_id, variants);
+        }
+        for group in backup.items(Resource::Groups).to_vec() {
+            let group_id = resource_id(&group);
+            let products = client
+                .groups_list_products_all_pages(group_id.clone())
+                .await?;
+            let product_ids = products.iter().map(resource_id).collect();
+            backup.group_products.insert(group_id, product_ids);
+        }
+        return Ok(backup);
+    }
+
+    /// Recreate the catalog of a backup in the store of this client.
+    ///
+    /// Sections, products, variants, groups (with their products), coupons and blacklist rules are created
+    /// as new items, with references between them remapped to the new IDs. Invoices, tickets and feedback
+    /// can't be created through the API and are reported as skipped, as is every item whose creation failed.
+    ///
+    /// Restoring into a store that still has the original items creates duplicates.
+    pub async fn restore(&self, backup: &Backup) -> Result<RestoreReport, BackupError> {
+        let mut report = RestoreReport::default();
+
+        for section in backup.items(Resource::Sections) {
+            let res = self.sections_create(creatable(section).to_string()).await;
+            record(&mut report, "sections", section, res).await;
+        }
+
+        for product in backup.items(Resource::Products) {
+            let mut data = creatable(product);
+            remap(&report, &mut data, "section_id", "sections");
+            let res = self.products_create(data.to_string()).await;
+            let new_id = record(&mut report, "products", product, res).await;
+
+            let product_id = resource_id(product);
+            let variants = backup
+                .variants
+                .get(&product_id)
+                .map_or(&[][..], Vec::as_slice);
+            for variant in variants {
+                match &new_id {
+                    Some(new_product_id) => {
+                        let res = self
+                            .variants_create(new_product_id.clone(), creatable(variant).to_string())
+                            .await;
+                        record(&mut report, "variants", variant, res).await;
+                    }
+                    None => report.skip(
+                        "variants",
+                        resource_id(variant),
+                        "its product was not restored".to_string(),
+                    ),
+                }
+            }
+        }
+
+        for group in backup.items(Resource::Groups) {
+            let res = self.groups_create(creatable(group).to_string()).await;
+            let new_group_id = match record(&mut report, "groups", group, res).await {
+                Some(id) => id,
+                None => continue,
+            };
+            let product_ids: Vec<&String> = backup
+                .group_products
+                .get(&resource_id(group))
+                .map_or(&[][..], Vec::as_slice)
+                .iter()
+                .filter_map(|id| report.new_id("products", id))
+                .collect();
+            if product_ids.is_empty() {
+                continue;
+            }
+            let data = json!({ "products": product_ids }).to_string();
+            let res = self.groups_add_products(new_group_id, data).await;
+            if let Err(reason) = check_response(res).await {
+                report.skip("group_products", resource_id(group), reason);
+            }
+        }
+
+        for coupon in backup.items(Resource::Coupons) {
+            let mut data = creatable(coupon);
+            if let Some(products) = data["products"].as_array_mut() {
+                for product in products.iter_mut() {
+                    let new_id = id_string(product).and_then(|id| report.new_id("products", &id));
+                    if let Some(new_id) = new_id {
+                        *product = json!(new_id);
+                    }
+                }
+            }
+            let res = self.coupons_create(data.to_string()).await;
+            record(&mut report, "coupons", coupon, res).await;
+        }
+
+        for rule in backup.items(Resource::Blacklists) {
+            let res = self
+                .blacklist_create_rule(creatable(rule).to_string())
+                .await;
+            record(&mut report, "blacklists", rule, res).await;
+        }
+
+        for resource in [Resource::Invoices, Resource::Tickets, Resource::Feedback] {
+            for item in backup.items(resource) {
+                report.skip(
+                    resource.name(),
+                    resource_id(item),
+                    "can't be created through the API".to_string(),
+                );
+            }
+        }
+
+        return Ok(report);
+    }
+}
+
+/// Replace an ID attribute of ``data`` with the new ID of the referenced item, if it was restored.
+fn remap(report: &RestoreReport, data: &mut Value, field: &str, resource: &str) {
+    let old_id = match id_string(&data[field]) {
+        Some(id) => id,
+        None => return,
+    };
+    match report.new_id(resource, &old_id) {
+        Some(new_id) => data[field] = json!(new_id),
+        None => data[field] = Value::Null,
+    }
+}
+
+/// Record the new ID of a restored item, or why it was skipped. Returns the new ID.
+///
+/// Items whose create response has no ID are skipped too, as nothing referencing them can be restored.
+async fn record(
+    report: &mut RestoreReport,
+    resource: &str,
+    item: &Value,
+    res: Result<reqwest::Response, crate::Error>,
+) -> Option<String> {
+    let old_id = resource_id(item);
+    match check_response(res).await {
+        Ok(created) => {
+            let new_id = resource_id(&created);
+            if new_id.is_empty() || new_id == "null" {
+                let reason = "created, but the API response has no ID".to_string();
+                report.skip(resource, old_id, reason);
+                return None;
+            }
+            report
+                .ids
+                .insert((resource.to_string(), old_id), new_id.clone());
+            return Some(new_id);
+        }
+        Err(reason) => {
+            report.skip(resource, old_id, reason);
+            return None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::transport::mock::{self, MockTransport};
+
+    /// The store backed up: a section with a product, its variant, a group and a coupon for the product, a
+    /// blacklist rule and an invoice.
+    fn source() -> MockTransport {
+        return MockTransport::new(|line, _| {
+            let data = match line.split('?').next().unwrap() {
+                "GET v1/sections" => json!([{ "id": 1, "title": "Games" }]),
+                "GET v2/products" => json!([{ "id": 2, "slug": "steam-key", "section_id": 1 }]),
+                "GET v2/products/2/variants" => {
+                    json!([{ "id": 3, "product_id": 2, "title": "Single" }])
+                }
+                "GET v2/groups" => json!([{ "id": 4, "title": "Bundles" }]),
+                "GET v2/groups/4/products" => json!([{ "id": 2 }]),
+                "GET v1/coupons" => json!([{ "id": 5, "code": "SAVE10", "products": [2, "9"] }]),
+                "GET v1/blacklists" => {
+                    json!([{ "id": 6, "type": "EMAIL", "data": "spam@example.com" }])
+                }
+                "GET v2/invoices" => json!([{ "id": 7 }]),
+                _ => json!([]),
+            };
+            return mock::json(200, json!({ "data": data }));
+        });
+    }
+
+    /// An empty store giving created items the IDs 100, 101, ..., except for blacklist rules, whose create response
+    /// has no ID.
+    fn target() -> MockTransport {
+        let next_id = AtomicUsize::new(100);
+        return MockTransport::new(move |line, _| {
+            if line == "POST v1/blacklists" {
+                return mock::json(201, json!({ "data": {} }));
+            }
+            let id = next_id.fetch_add(1, Ordering::SeqCst);
+            return mock::json(201, json!({ "data": { "id": id } }));
+        });
+    }
+
+    #[tokio::test]
+    async fn restores_a_backup_with_new_ids() {
+        let backup = mock::client(&source()).backup().await.unwrap();
+        assert_eq!(backup.items(Resource::Products).len(), 1);
+        assert_eq!(backup.variants["2"].len(), 1);
+        assert_eq!(backup.group_products["4"], ["2"]);
+
+        // The backup survives a round trip through its file format.
+        let backup: Backup = serde_json::from_slice(&serde_json::to_vec(&backup).unwrap()).unwrap();
+
+        let transport = target();
+        let report = mock::client(&transport).restore(&backup).await.unwrap();
+        assert_eq!(report.new_id("sections", "1").unwrap(), "100");
+        assert_eq!(report.new_id("products", "2").unwrap(), "101");
+        assert_eq!(report.new_id("variants", "3").unwrap(), "102");
+        assert_eq!(report.new_id("groups", "4").unwrap(), "103");
+        assert_eq!(report.new_id("coupons", "5").unwrap(), "105");
+        assert_eq!(report.new_id("blacklists", "6"), None);
+
+        let product = &transport.bodies("POST v2/products")[0];
+        assert_eq!(product["section_id"], "100");
+        assert_eq!(product.get("id"), None);
+        assert_eq!(transport.count("POST v2/products/101/variants"), 1);
+        let attached = &transport.bodies("POST v2/groups/103/products/attach")[0];
+        assert_eq!(attached["products"], json!(["101"]));
+        // References to products outside the backup are kept as they are.
+        assert_eq!(
+            transport.bodies("POST v1/coupons")[0]["products"],
+            json!(["101", "9"])
+        );
+
+        let skipped: Vec<(&str, &str, &str)> = report
+            .skipped
+            .iter()
+            .map(|item| {
+                (
+                    item.resource.as_str(),
+                    item.id.as_str(),
+                    item.reason.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            skipped,
+            [
+                ("blacklists", "6", "created, but the API response has no ID"),
+                ("invoices", "7", "can't be created through the API"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn skips_variants_of_products_created_without_an_id() {
+        let transport = MockTransport::new(|line, _| match line {
+            "POST v2/products" => mock::json(201, json!({ "data": { "id": null } })),
+            _ => mock::json(201, json!({ "data": { "id": 100 } })),
+        });
+        let backup = mock::client(&source()).backup().await.unwrap();
+        let report = mock::client(&transport).restore(&backup).await.unwrap();
+
+        assert_eq!(report.new_id("products", "2"), None);
+        assert_eq!(transport.count("POST v2/products/"), 0);
+        assert_eq!(transport.count("POST v2/groups/100/products/attach"), 0);
+        let skipped: Vec<(&str, &str)> = report
+            .skipped
+            .iter()
+            .map(|item| (item.resource.as_str(), item.reason.as_str()))
+            .take(2)
+            .collect();
+        assert_eq!(
+            skipped,
+            [
+                ("products", "created, but the API response has no ID"),
+                ("variants", "its product was not restored"),
+            ]
+        );
+    }
+}
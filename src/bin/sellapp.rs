@@ -0,0 +1,75 @@
+#![allow(clippy::needless_return)]
+
+use std::{
+    env,
+    io::{self, BufRead, IsTerminal, Write},
+    process::ExitCode,
+};
+
+use sellapp::catalog::CatalogConfig;
+
+const USAGE: &str = "Usage: sellapp <plan|apply> [--yes] <catalog.toml|catalog.yaml>
+
+apply asks for confirmation before changing the store, unless --yes (or --auto-approve) is given.
+
+Reads the API key from SELLAPP_API_KEY, and the optional store slug from SELLAPP_STORE.";
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let approved = args
+        .iter()
+        .any(|arg| arg == "--yes" || arg == "--auto-approve");
+    args.retain(|arg| arg != "--yes" && arg != "--auto-approve");
+    let (command, path) = match &args[..] {
+        [command, path] if command == "plan" || command == "apply" => (command, path),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    let api_key = match env::var("SELLAPP_API_KEY") {
+        Ok(api_key) => api_key,
+        Err(_) => {
+            eprintln!("SELLAPP_API_KEY is not set.\n\n{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    let store_slug = env::var("SELLAPP_STORE").unwrap_or_default();
+    let api = sellapp::init(&api_key, &store_slug);
+
+    let result = async {
+        let config = CatalogConfig::load(path)?;
+        let plan = api.catalog_plan(&config).await?;
+        print!("{}", plan);
+        if command == "apply" && !plan.is_empty() {
+            if !approved && !confirm(plan.changes.len())? {
+                println!("Apply cancelled.");
+                return Ok(());
+            }
+            api.catalog_apply(&plan).await?;
+            println!("Applied {} changes.", plan.changes.len());
+        }
+        return Ok::<(), Box<dyn std::error::Error>>(());
+    };
+
+    if let Err(error) = result.await {
+        eprintln!("Error: {}", error);
+        return ExitCode::FAILURE;
+    }
+    return ExitCode::SUCCESS;
+}
+
+/// Ask whether to apply the plan. Without a terminal to ask on, ``--yes`` is required.
+fn confirm(changes: usize) -> io::Result<bool> {
+    if !io::stdin().is_terminal() {
+        return Err(io::Error::other(
+            "refusing to apply without confirmation, pass --yes to apply non-interactively",
+        ));
+    }
+    print!("Apply {} changes? Only \"yes\" is accepted: ", changes);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    return Ok(answer.trim() == "yes");
+}
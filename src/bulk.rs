@@ -0,0 +1,442 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use futures::{stream, StreamExt};
+use reqwest::{Response, StatusCode};
+
+use crate::Error;
+use serde_json::Value;
+
+use crate::{
+    middleware::{retry_after, RateLimit},
+    rt::{self, Instant},
+    SellAppClient,
+};
+
+/// How many times an operation answered with **429 Too Many Requests** is sent again.
+const RATE_LIMITED_RETRIES: u32 = 3;
+
+/// A single mutating call to run as part of a bulk operation.
+///
+/// ``data`` is the same JSON you would pass to the matching client method.
+#[derive(Debug, Clone)]
+pub enum BulkOperation {
+    CreateBlacklistRule {
+        data: String,
+    },
+    UpdateBlacklistRule {
+        rule_id: String,
+        data: String,
+    },
+    DeleteBlacklistRule {
+        rule_id: String,
+    },
+    CreateCoupon {
+        data: String,
+    },
+    UpdateCoupon {
+        coupon_id: String,
+        data: String,
+    },
+    DeleteCoupon {
+        coupon_id: String,
+    },
+    CreateGroup {
+        data: String,
+    },
+    UpdateGroup {
+        group_id: String,
+        data: String,
+    },
+    DeleteGroup {
+        group_id: String,
+    },
+    CreateProduct {
+        data: String,
+    },
+    UpdateProduct {
+        product_id: String,
+        data: String,
+    },
+    DeleteProduct {
+        product_id: String,
+    },
+    CreateVariant {
+        product_id: String,
+        data: String,
+    },
+    UpdateVariant {
+        product_id: String,
+        variant_id: String,
+        data: String,
+    },
+    DeleteVariant {
+        product_id: String,
+        variant_id: String,
+    },
+    CreateSection {
+        data: String,
+    },
+    UpdateSection {
+        section_id: String,
+        data: String,
+    },
+    DeleteSection {
+        section_id: String,
+    },
+}
+
+impl BulkOperation {
+    async fn send(&self, client: &SellAppClient) -> Result<Response, Error> {
+        return match self.clone() {
+            BulkOperation::CreateBlacklistRule { data } => client.blacklist_create_rule(data).await,
+            BulkOperation::UpdateBlacklistRule { rule_id, data } => {
+                client.blacklist_update_rule(rule_id, data).await
+            }
+            BulkOperation::DeleteBlacklistRule { rule_id } => {
+                client.blacklist_delete_rule(rule_id).await
+            }
+            BulkOperation::CreateCoupon { data } => client.coupons_create(data).await,
+            BulkOperation::UpdateCoupon { coupon_id, data } => {
+                client.coupons_update(coupon_id, data).await
+            }
+            BulkOperation::DeleteCoupon { coupon_id } => client.coupons_delete(coupon_id).await,
+            BulkOperation::CreateGroup { data } => client.groups_create(data).await,
+            BulkOperation::UpdateGroup { group_id, data } => {
+                client.groups_update(group_id, data).await
+            }
+            BulkOperation::DeleteGroup { group_id } => client.groups_delete(group_id).await,
+            BulkOperation::CreateProduct { data } => client.products_create(data).await,
+            BulkOperation::UpdateProduct { product_id, data } => {
+                client.products_update(product_id, data).await
+            }
+            BulkOperation::DeleteProduct { product_id } => client.products_delete(product_id).await,
+            BulkOperation::CreateVariant { product_id, data } => {
+                client.variants_create(product_id, data).await
+            }
+            BulkOperation::UpdateVariant {
+                product_id,
+                variant_id,
+                data,
+            } => client.variants_update(product_id, variant_id, data).await,
+            BulkOperation::DeleteVariant {
+                product_id,
+                variant_id,
+            } => client.variants_delete(product_id, variant_id).await,
+            BulkOperation::CreateSection { data } => client.sections_create(data).await,
+            BulkOperation::UpdateSection { section_id, data } => {
+                client.sections_update(section_id, data).await
+            }
+            BulkOperation::DeleteSection { section_id } => client.sections_delete(section_id).await,
+        };
+    }
+}
+
+/// Why a single bulk operation failed.
+#[derive(Debug)]
+pub enum BulkError {
+    /// The request could not be sent, or its response could not be read.
+    Request(Error),
+    /// The API responded with an error status code.
+    Api { status: StatusCode, body: Value },
+    /// The operation was not run, because an earlier operation failed with ``stop_on_error`` enabled.
+    Skipped,
+}
+
+impl fmt::Display for BulkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            BulkError::Request(error) => write!(f, "request failed: {}", error),
+            BulkError::Api { status, body } => write!(f, "API returned {}: {}", status, body),
+            BulkError::Skipped => write!(f, "skipped after an earlier operation failed"),
+        };
+    }
+}
+
+impl std::error::Error for BulkError {}
+
+/// Result of a single bulk operation.
+#[derive(Debug)]
+pub struct BulkItemReport {
+    /// Position of the operation in the input.
+    pub index: usize,
+    pub operation: BulkOperation,
+    /// The JSON response of the API on success, ``Value::Null`` if the response had no body.
+    pub result: Result<Value, BulkError>,
+}
+
+/// Runs many ``BulkOperation``s with a bounded amount of requests in flight, created with ``SellAppClient::bulk``.
+///
+/// Operations answered with **429 Too Many Requests** are sent again up to 3 times. Every operation of the run waits
+/// for the ``Retry-After`` delay of the response first, or 1, 2 then 4 seconds if the API didn't send one.
+pub struct BulkExecutor<'a> {
+    client: &'a SellAppClient,
+    concurrency: usize,
+    stop_on_error: bool,
+    rate_limit: Option<RateLimit>,
+}
+
+impl SellAppClient {
+    /// Create a ``BulkExecutor`` that runs 4 operations at a time and keeps going after failures.
+    ///
+    /// ```no_run
+    /// use sellapp::bulk::BulkOperation;
+    ///
+    /// # async fn run() {
+    /// let sellapp_api = sellapp::init("your_api_key", "");
+    /// let updates = ["1", "2", "3"].iter().map(|variant_id| BulkOperation::UpdateVariant {
+    ///     product_id: "10".to_string(),
+    ///     variant_id: variant_id.to_string(),
+    ///     data: r#"{"price": "9.99"}"#.to_string(),
+    /// });
+    ///
+    /// let report = sellapp_api.bulk().with_concurrency(8).run(updates).await;
+    /// for item in report.iter().filter(|item| item.result.is_err()) {
+    ///     println!("Operation {} failed: {:?}", item.index, item.result);
+    /// }
+    /// # }
+    /// ```
+    pub fn bulk(&self) -> BulkExecutor<'_> {
+        return BulkExecutor {
+            client: self,
+            concurrency: 4,
+            stop_on_error: false,
+            rate_limit: None,
+        };
+    }
+}
+
+impl BulkExecutor<'_> {
+    /// Maximum amount of operations running at the same time, defaults to 4.
+    ///
+    /// This bounds requests in flight, not requests per second, see ``with_rate_limit`` for that.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        return self;
+    }
+
+    /// Take a slot from ``rate_limit`` before sending each operation, and pause it when the API answers
+    /// **429 Too Many Requests**.
+    ///
+    /// Pass a clone of the ``middleware::RateLimit`` installed on the client to share its bucket with the other
+    /// calls of the client. The middleware already takes a slot for each request then, so the executor doesn't
+    /// take another one and only adds the pause on 429.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use sellapp::middleware::RateLimit;
+    ///
+    /// let rate_limit = RateLimit::new(60, Duration::from_secs(60));
+    /// let sellapp_api = sellapp::init("your_api_key", "");
+    /// let bulk = sellapp_api.bulk().with_concurrency(8).with_rate_limit(rate_limit);
+    /// ```
+    pub fn with_rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.rate_limit = Some(rate_limit);
+        return self;
+    }
+
+    /// Skip every operation that has not started yet once one fails, defaults to ``false``.
+    ///
+    /// Operations that are already running when the failure happens still finish.
+    pub fn with_stop_on_error(mut self, stop_on_error: bool) -> Self {
+        self.stop_on_error = stop_on_error;
+        return self;
+    }
+
+    /// Run all ``operations``, and return a report for each of them in input order.
+    pub async fn run(
+        &self,
+        operations: impl IntoIterator<Item = BulkOperation>,
+    ) -> Vec<BulkItemReport> {
+        let failed = AtomicBool::new(false);
+        let paused_until = Mutex::new(None);
+
+        let mut reports: Vec<BulkItemReport> = stream::iter(operations.into_iter().enumerate())
+            .map(|(index, operation)| {
+                let failed = &failed;
+                let paused_until = &paused_until;
+                async move {
+                    if self.stop_on_error && failed.load(Ordering::SeqCst) {
+                        return BulkItemReport {
+                            index,
+                            operation,
+                            result: Err(BulkError::Skipped),
+                        };
+                    }
+                    let result = self.execute(&operation, paused_until).await;
+                    if result.is_err() {
+                        failed.store(true, Ordering::SeqCst);
+                    }
+                    return BulkItemReport {
+                        index,
+                        operation,
+                        result,
+                    };
+                }
+            })
+            .buffer_unordered(self.concurrency)
+            .collect()
+            .await;
+
+        reports.sort_by_key(|report| report.index);
+        return reports;
+    }
+
+    async fn execute(
+        &self,
+        operation: &BulkOperation,
+        paused_until: &Mutex<Option<Instant>>,
+    ) -> Result<Value, BulkError> {
+        // The middleware of the client takes a slot itself if the limiter is installed there.
+        let acquire = self
+            .rate_limit
+            .as_ref()
+            .filter(|rate_limit| !self.client.has_rate_limit(rate_limit));
+        let mut attempt = 0;
+        let res = loop {
+            wait_until(paused_until).await;
+            if let Some(rate_limit) = acquire {
+                rate_limit.acquire().await;
+            }
+            let res = operation
+                .send(self.client)
+                .await
+                .map_err(BulkError::Request)?;
+            if res.status() != StatusCode::TOO_MANY_REQUESTS || attempt >= RATE_LIMITED_RETRIES {
+                break res;
+            }
+
+            let delay = retry_after(&res).unwrap_or(Duration::from_secs(1 << attempt));
+            let until = Instant::now() + delay;
+            {
+                let mut paused_until = paused_until.lock().unwrap();
+                if paused_until.is_none_or(|paused_until| paused_until < until) {
+                    *paused_until = Some(until);
+                }
+            }
+            if let Some(rate_limit) = &self.rate_limit {
+                rate_limit.pause(until);
+            }
+            attempt += 1;
+        };
+        let status = res.status();
+        let body = res
+            .bytes()
+            .await
+            .map_err(|error| BulkError::Request(error.into()))?;
+        let body = serde_json::from_slice(&body).unwrap_or(Value::Null);
+
+        if !status.is_success() {
+            return Err(BulkError::Api { status, body });
+        }
+        return Ok(body);
+    }
+}
+
+/// Wait until the pause set after a **429 Too Many Requests** response is over.
+async fn wait_until(paused_until: &Mutex<Option<Instant>>) {
+    loop {
+        let now = Instant::now();
+        let wait = match *paused_until.lock().unwrap() {
+            Some(until) if until > now => until - now,
+            _ => return,
+        };
+        rt::sleep(wait).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::transport::mock::{self, MockTransport};
+
+    fn updates(count: usize) -> Vec<BulkOperation> {
+        return (0..count)
+            .map(|index| BulkOperation::UpdateProduct {
+                product_id: index.to_string(),
+                data: "{}".to_string(),
+            })
+            .collect();
+    }
+
+    /// A store answering the first ``limited`` requests with **429 Too Many Requests**.
+    fn store(limited: usize, retry_after: &'static str) -> MockTransport {
+        let requests = AtomicUsize::new(0);
+        return MockTransport::new(move |_, _| {
+            if requests.fetch_add(1, Ordering::SeqCst) < limited {
+                let mut res = mock::json(429, json!({ "message": "Too Many Attempts." }));
+                res.headers_mut()
+                    .insert("Retry-After", retry_after.parse().unwrap());
+                return res;
+            }
+            return mock::json(200, json!({ "data": { "id": 1 } }));
+        });
+    }
+
+    #[tokio::test]
+    async fn waits_and_retries_after_429() {
+        let transport = store(1, "1");
+        let client = mock::client(&transport);
+        let rate_limit = RateLimit::new(100, Duration::from_secs(1));
+
+        let started = Instant::now();
+        let reports = client
+            .bulk()
+            .with_concurrency(1)
+            .with_rate_limit(rate_limit.clone())
+            .run(updates(2))
+            .await;
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        assert!(reports.iter().all(|report| report.result.is_ok()));
+        assert_eq!(
+            transport.requests(),
+            [
+                "PATCH v2/products/0",
+                "PATCH v2/products/0",
+                "PATCH v2/products/1"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_the_last_retry() {
+        let transport = store(usize::MAX, "0");
+        let client = mock::client(&transport);
+        let reports = client.bulk().run(updates(1)).await;
+        match &reports[0].result {
+            Err(BulkError::Api { status, .. }) => {
+                assert_eq!(*status, StatusCode::TOO_MANY_REQUESTS)
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+        assert_eq!(
+            transport.requests().len(),
+            1 + RATE_LIMITED_RETRIES as usize
+        );
+    }
+
+    #[tokio::test]
+    async fn takes_one_slot_per_call_from_the_client_rate_limit() {
+        let transport = store(0, "0");
+        let rate_limit = RateLimit::new(2, Duration::from_secs(60));
+        let client = mock::client(&transport).with_middleware(rate_limit.clone());
+
+        // A second slot per call would wait 30 seconds for the bucket to refill.
+        let bulk = client.bulk().with_rate_limit(rate_limit);
+        let run = bulk.run(updates(2));
+        let reports = tokio::time::timeout(Duration::from_secs(5), run)
+            .await
+            .unwrap();
+        assert!(reports.iter().all(|report| report.result.is_ok()));
+    }
+}
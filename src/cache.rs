@@ -0,0 +1,418 @@
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use bytes::Bytes;
+use reqwest::{
+    header::{HeaderMap, HeaderValue, ETAG, IF_NONE_MATCH},
+    Method, Request, Response, ResponseBuilderExt, StatusCode, Url,
+};
+
+use crate::{middleware::LocalResponse, rt::Instant};
+
+/// A successful GET response stored by the cache.
+#[derive(Clone)]
+pub struct CachedResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+    pub url: Url,
+    /// When the response was fetched or last revalidated.
+    pub stored_at: Instant,
+}
+
+impl CachedResponse {
+    fn etag(&self) -> Option<&HeaderValue> {
+        return self.headers.get(ETAG);
+    }
+
+    fn to_response(&self) -> Response {
+        let mut builder = http::Response::builder()
+            .status(self.status)
+            .url(self.url.clone());
+        if let Some(headers) = builder.headers_mut() {
+            headers.extend(self.headers.clone());
+        }
+        let res = builder.body(self.body.clone()).unwrap();
+        return Response::from(res);
+    }
+}
+
+/// Storage backend for the response cache.
+///
+/// Keys are opaque strings, but always start with the resource path of the request (e.g. **"v2/products/12"**),
+/// which ``remove_prefix`` relies on for invalidation.
+pub trait CacheStore: Send + Sync {
+    /// Get a stored response by key.
+    fn get(&self, key: &str) -> Option<CachedResponse>;
+
+    /// Store a response, replacing any existing entry with the same key.
+    fn put(&self, key: String, entry: CachedResponse);
+
+    /// Remove every entry whose key starts with ``prefix``.
+    fn remove_prefix(&self, prefix: &str);
+}
+
+/// In-memory cache store that evicts the least recently used entry once it is full.
+pub struct MemoryCache {
+    capacity: usize,
+    state: Mutex<MemoryCacheState>,
+}
+
+struct MemoryCacheState {
+    tick: u64,
+    entries: HashMap<String, (u64, CachedResponse)>,
+}
+
+impl MemoryCache {
+    /// Create a store holding at most ``capacity`` responses.
+    pub fn new(capacity: usize) -> Self {
+        return MemoryCache {
+            capacity,
+            state: Mutex::new(MemoryCacheState {
+                tick: 0,
+                entries: HashMap::new(),
+            }),
+        };
+    }
+}
+
+impl CacheStore for MemoryCache {
+    fn get(&self, key: &str) -> Option<CachedResponse> {
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+        let (used, entry) = state.entries.get_mut(key)?;
+        *used = tick;
+        return Some(entry.clone());
+    }
+
+    fn put(&self, key: String, entry: CachedResponse) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+        if !state.entries.contains_key(&key) && state.entries.len() >= self.capacity {
+            let oldest = state
+                .entries
+                .iter()
+                .min_by_key(|(_, (used, _))| *used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                state.entries.remove(&oldest);
+            }
+        }
+        state.entries.insert(key, (tick, entry));
+    }
+
+    fn remove_prefix(&self, prefix: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.entries.retain(|key, _| !key.starts_with(prefix));
+    }
+}
+
+/// Opt-in cache for GET endpoints, attached with ``SellAppClient::with_cache``.
+///
+/// Fresh entries are returned without contacting the API. Stale entries that carry an ``ETag`` are revalidated
+/// with ``If-None-Match``, and are reused if the API responds with **304 Not Modified**.
+///
+/// Any successful non-GET call made through the same client invalidates all cached entries of that resource,
+/// e.g. updating a variant drops every cached ``v2/products`` response.
+///
+/// Helpers that act on the current state of the store never read from the cache, whatever the TTL: waiting for
+/// checkout completion, ``watcher::InvoiceWatcher`` polls, replacement checks, serial updates, idempotency lookups,
+/// and the catalog, backup, copy and sync workflows. Their responses still refresh the cached entries.
+pub struct ResponseCache {
+    store: Box<dyn CacheStore>,
+    default_ttl: Duration,
+    ttls: HashMap<String, Duration>,
+}
+
+pub(crate) enum CacheLookup {
+    Fresh(Response),
+    Stale(CachedResponse),
+    Miss,
+}
+
+impl ResponseCache {
+    /// Create a cache backed by an in-memory LRU store of 1000 entries.
+    ///
+    /// ``default_ttl``: How long responses stay fresh, unless overridden for their resource with ``with_ttl``.
+    pub fn new(default_ttl: Duration) -> Self {
+        return Self::with_store(MemoryCache::new(1000), default_ttl);
+    }
+
+    /// Create a cache backed by a custom store.
+    pub fn with_store(store: impl CacheStore + 'static, default_ttl: Duration) -> Self {
+        return ResponseCache {
+            store: Box::new(store),
+            default_ttl,
+            ttls: HashMap::new(),
+        };
+    }
+
+    /// Override the TTL of a resource.
+    ///
+    /// ``resource``: Name of the resource as it appears in the API path, e.g. **"products"** or **"sections"**.
+    ///
+    /// A TTL of zero disables caching for that resource.
+    pub fn with_ttl(mut self, resource: &str, ttl: Duration) -> Self {
+        self.ttls.insert(resource.to_string(), ttl);
+        return self;
+    }
+
+    fn ttl(&self, path: &str) -> Duration {
+        let resource = path.split('/').nth(1).unwrap_or_default();
+        return *self.ttls.get(resource).unwrap_or(&self.default_ttl);
+    }
+
+    pub(crate) fn lookup(&self, request: &mut Request) -> CacheLookup {
+        let path = api_path(request.url());
+        if request.method() != Method::GET || self.ttl(&path).is_zero() {
+            return CacheLookup::Miss;
+        }
+        let entry = match self.store.get(&cache_key(request)) {
+            Some(entry) => entry,
+            None => return CacheLookup::Miss,
+        };
+        if entry.stored_at.elapsed() < self.ttl(&path) {
+            let mut response = entry.to_response();
+            response.extensions_mut().insert(LocalResponse::CacheHit);
+            return CacheLookup::Fresh(response);
+        }
+        match entry.etag() {
+            Some(etag) => {
+                request.headers_mut().insert(IF_NONE_MATCH, etag.clone());
+                return CacheLookup::Stale(entry);
+            }
+            None => return CacheLookup::Miss,
+        }
+    }
+
+    /// Update the cache with the response of a request that was actually sent.
+    pub(crate) async fn store(
+        &self,
+        method: &Method,
+        key: String,
+        stale: Option<CachedResponse>,
+        response: Response,
+    ) -> Result<Response, reqwest::Error> {
+        let path = api_path(response.url());
+        if method != Method::GET {
+            if response.status().is_success() {
+                self.invalidate(&path);
+            }
+            return Ok(response);
+        }
+        if self.ttl(&path).is_zero() {
+            return Ok(response);
+        }
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some(mut entry) = stale {
+                entry.stored_at = Instant::now();
+                self.store.put(key, entry.clone());
+                return Ok(entry.to_response());
+            }
+        }
+        if !response.status().is_success() {
+            return Ok(response);
+        }
+
+        let entry = CachedResponse {
+            status: response.status(),
+            headers: response.headers().clone(),
+            url: response.url().clone(),
+            body: response.bytes().await?,
+            stored_at: Instant::now(),
+        };
+        self.store.put(key, entry.clone());
+        return Ok(entry.to_response());
+    }
+
+    /// Drop every cached response belonging to the resource of ``path``.
+    fn invalidate(&self, path: &str) {
+        let resource: Vec<&str> = path.split('/').take(2).collect();
+        self.store.remove_prefix(&resource.join("/"));
+    }
+}
+
+/// Key under which the response to ``request`` is cached.
+pub(crate) fn cache_key(request: &Request) -> String {
+    let mut key = api_path(request.url());
+    if let Some(query) = request.url().query() {
+        key.push('?');
+        key.push_str(query);
+    }
+    if let Some(store) = request.headers().get("X-STORE") {
+        key.push_str(" store=");
+        key.push_str(store.to_str().unwrap_or_default());
+    }
+    return key;
+}
+
+/// Path of a request relative to the API root, e.g. **"v2/products/12"**.
+fn api_path(url: &Url) -> String {
+    let path = url.path().trim_start_matches('/');
+    return path.strip_prefix("api/").unwrap_or(path).to_string();
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{
+        replacement::ReplacementRequest,
+        transport::mock::{self, MockTransport},
+    };
+
+    fn entry(body: &'static str) -> CachedResponse {
+        return CachedResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(body.as_bytes()),
+            url: Url::parse("https://sell.app/api/v2/products").unwrap(),
+            stored_at: Instant::now(),
+        };
+    }
+
+    /// A store with one product, group and completed invoice, and nothing else.
+    fn store() -> MockTransport {
+        return MockTransport::new(|line, _| {
+            let path = line.split('?').next().unwrap();
+            return match path {
+                "GET v2/products" | "GET v2/groups/2/products" => mock::json(
+                    200,
+                    json!({ "data": [{ "id": 1, "slug": "key", "title": "Key" }] }),
+                ),
+                "GET v2/groups" => {
+                    mock::json(200, json!({ "data": [{ "id": 2, "title": "Games" }] }))
+                }
+                "GET v2/invoices/3" => mock::json(
+                    200,
+                    json!({ "data": {
+                        "id": 3,
+                        "status": { "status": { "status": "COMPLETED" } },
+                        "products": [{ "id": 1, "variants": [{ "id": 4, "quantity": 1 }] }],
+                    } }),
+                ),
+                "GET v2/products/1/variants/4" => mock::json(200, json!({ "data": { "id": 4 } })),
+                _ if path.starts_with("GET ") => mock::json(200, json!({ "data": [] })),
+                _ => mock::json(200, json!({ "data": { "id": 5 } })),
+            };
+        });
+    }
+
+    #[test]
+    fn memory_cache_evicts_least_recently_used() {
+        let cache = MemoryCache::new(2);
+        cache.put("v2/products/1".to_string(), entry("1"));
+        cache.put("v2/products/2".to_string(), entry("2"));
+        assert!(cache.get("v2/products/1").is_some());
+        cache.put("v2/products/3".to_string(), entry("3"));
+
+        assert!(cache.get("v2/products/1").is_some());
+        assert!(cache.get("v2/products/2").is_none());
+        assert_eq!(cache.get("v2/products/3").unwrap().body, "3");
+
+        // Replacing an entry doesn't evict another one.
+        cache.put("v2/products/3".to_string(), entry("3b"));
+        assert!(cache.get("v2/products/1").is_some());
+        assert_eq!(cache.get("v2/products/3").unwrap().body, "3b");
+    }
+
+    #[test]
+    fn memory_cache_without_capacity_stores_nothing() {
+        let cache = MemoryCache::new(0);
+        cache.put("v2/products".to_string(), entry("[]"));
+        assert!(cache.get("v2/products").is_none());
+    }
+
+    #[test]
+    fn memory_cache_removes_by_prefix() {
+        let cache = MemoryCache::new(10);
+        cache.put("v2/products".to_string(), entry("[]"));
+        cache.put("v2/products/1/variants".to_string(), entry("[]"));
+        cache.put("v2/groups".to_string(), entry("[]"));
+        cache.remove_prefix("v2/products");
+        assert!(cache.get("v2/products").is_none());
+        assert!(cache.get("v2/products/1/variants").is_none());
+        assert!(cache.get("v2/groups").is_some());
+    }
+
+    #[tokio::test]
+    async fn serves_fresh_responses_and_invalidates_on_writes() {
+        let transport = store();
+        let client =
+            mock::client(&transport).with_cache(ResponseCache::new(Duration::from_secs(60)));
+
+        client.products_list_all("?page=1").await.unwrap();
+        let res = client.products_list_all("?page=1").await.unwrap();
+        assert_eq!(res.json::<Value>().await.unwrap()["data"][0]["id"], 1);
+        client.products_list_all("?page=2").await.unwrap();
+        assert_eq!(transport.count("GET v2/products"), 2);
+
+        client.groups_list_all("").await.unwrap();
+        client
+            .products_update("1".to_string(), "{}".to_string())
+            .await
+            .unwrap();
+        client.products_list_all("?page=1").await.unwrap();
+        client.groups_list_all("").await.unwrap();
+        assert_eq!(transport.count("GET v2/products"), 3);
+        assert_eq!(transport.count("GET v2/groups"), 1);
+    }
+
+    #[tokio::test]
+    async fn helpers_skip_the_cache() {
+        let transport = store();
+        let client =
+            mock::client(&transport).with_cache(ResponseCache::new(Duration::from_secs(60)));
+        let products = "GET v2/products?limit=100&page=1";
+        let group_products = "GET v2/groups/2/products?limit=100&page=1";
+
+        // Warm the cache with the requests the helpers make.
+        client.products_list_all("?limit=100&page=1").await.unwrap();
+        client
+            .groups_list_products("2".to_string(), "?limit=100&page=1")
+            .await
+            .unwrap();
+        client.invoices_get("3".to_string()).await.unwrap();
+        client
+            .variants_get("1".to_string(), "4".to_string())
+            .await
+            .unwrap();
+        client.products_list_all("?limit=100&page=1").await.unwrap();
+        assert_eq!(transport.count(products), 1);
+
+        let backup = client.backup().await.unwrap();
+        assert_eq!(backup.group_products["2"], vec!["1".to_string()]);
+        assert_eq!(transport.count(products), 2);
+        assert_eq!(transport.count(group_products), 2);
+        // The responses of the helpers still refresh the cache.
+        client.products_list_all("?limit=100&page=1").await.unwrap();
+        assert_eq!(transport.count(products), 2);
+
+        client
+            .copy_catalog("store", "other", |_| true)
+            .await
+            .unwrap();
+        assert_eq!(transport.count(products), 4);
+        // Both stores are answered by the same transport, so the source and target are listed.
+        assert_eq!(transport.count(group_products), 4);
+
+        let request = ReplacementRequest::new().with_variant("4", 1);
+        client
+            .invoices_replace("3".to_string(), &request)
+            .await
+            .unwrap();
+        let invoice_reads = transport
+            .requests()
+            .iter()
+            .filter(|line| *line == "GET v2/invoices/3")
+            .count();
+        assert_eq!(invoice_reads, 2);
+        assert_eq!(transport.count("GET v2/products/1/variants/4"), 2);
+    }
+}
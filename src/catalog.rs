@@ -0,0 +1,724 @@
+use std::{collections::HashMap, fmt, fs, io, path::Path};
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::{
+    money::Money,
+    resource::Resource,
+    util::{self, resource_id, unwrap_data},
+    SellAppClient,
+};
+
+/// A resource type that can be managed through a ``CatalogConfig``.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CatalogKind {
+    Products,
+    Variants,
+    Groups,
+    Sections,
+    Coupons,
+    Blacklists,
+}
+
+impl CatalogKind {
+    pub fn name(&self) -> &'static str {
+        return match self {
+            CatalogKind::Products => "products",
+            CatalogKind::Variants => "variants",
+            CatalogKind::Groups => "groups",
+            CatalogKind::Sections => "sections",
+            CatalogKind::Coupons => "coupons",
+            CatalogKind::Blacklists => "blacklists",
+        };
+    }
+
+    /// Attribute that identifies a resource of this kind across stores, instead of its Sell.App ID.
+    pub fn key_field(&self) -> &'static str {
+        return match self {
+            CatalogKind::Products => "slug",
+            CatalogKind::Variants => "title",
+            CatalogKind::Groups => "title",
+            CatalogKind::Sections => "title",
+            CatalogKind::Coupons => "code",
+            CatalogKind::Blacklists => "data",
+        };
+    }
+}
+
+/// Desired state of a single resource.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ResourceSpec {
+    /// Stable key of the resource, matched against its ``CatalogKind::key_field``.
+    pub key: String,
+    /// Attributes of the resource, as passed to its create and update methods.
+    #[serde(default)]
+    pub data: Map<String, Value>,
+}
+
+/// Desired state of a product, including its variants.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProductSpec {
+    #[serde(flatten)]
+    pub spec: ResourceSpec,
+    #[serde(default)]
+    pub variants: Vec<ResourceSpec>,
+}
+
+/// Desired state of a store catalog, usually loaded from a TOML or YAML file.
+///
+/// Prices can be set as a decimal amount in the currency the store uses for the resource, like **"4.99"**. They are
+/// compared with the price objects of the API (e.g. **{"price": "4.99", "currency": "USD"}**) as ``Money``.
+///
+/// ```toml
+/// prune = false
+///
+/// [[products]]
+/// key = "steam-key"
+/// data = { title = "Steam Key", description = "A random Steam key." }
+///
+/// [[products.variants]]
+/// key = "Single key"
+/// data = { price = "4.99" }
+///
+/// [[coupons]]
+/// key = "SUMMER10"
+/// data = { type = "PERCENTAGE", discount = 10 }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CatalogConfig {
+    /// Delete resources that exist in the store but not in the config. Defaults to ``false``.
+    pub prune: bool,
+    pub products: Vec<ProductSpec>,
+    pub groups: Vec<ResourceSpec>,
+    pub sections: Vec<ResourceSpec>,
+    pub coupons: Vec<ResourceSpec>,
+    pub blacklists: Vec<ResourceSpec>,
+}
+
+impl CatalogConfig {
+    pub fn from_toml_str(config: &str) -> Result<Self, CatalogError> {
+        return Ok(toml::from_str(config)?);
+    }
+
+    pub fn from_yaml_str(config: &str) -> Result<Self, CatalogError> {
+        return Ok(serde_yaml::from_str(config)?);
+    }
+
+    /// Load a config file, parsed as YAML if it ends with ``.yaml`` or ``.yml`` and as TOML otherwise.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CatalogError> {
+        let path = path.as_ref();
+        let config = fs::read_to_string(path)?;
+        return match path.extension().and_then(|extension| extension.to_str()) {
+            Some("yaml") | Some("yml") => Self::from_yaml_str(&config),
+            _ => Self::from_toml_str(&config),
+        };
+    }
+}
+
+/// A single planned change.
+#[derive(Debug, Clone)]
+pub enum Change {
+    Create {
+        kind: CatalogKind,
+        key: String,
+        /// Key of the product, for variants.
+        parent: Option<String>,
+        /// ID of the product, for variants of products that already exist.
+        parent_id: Option<String>,
+        data: Value,
+    },
+    Update {
+        kind: CatalogKind,
+        key: String,
+        id: String,
+        /// ID of the product, for variants.
+        parent_id: Option<String>,
+        /// Attributes whose value differs from the config.
+        changed: Vec<String>,
+        data: Value,
+    },
+    Delete {
+        kind: CatalogKind,
+        key: String,
+        id: String,
+        /// ID of the product, for variants.
+        parent_id: Option<String>,
+    },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            Change::Create {
+                kind, key, parent, ..
+            } => match parent {
+                Some(parent) => write!(f, "  + {} {}/{}", kind.name(), parent, key),
+                None => write!(f, "  + {} {}", kind.name(), key),
+            },
+            Change::Update {
+                kind, key, changed, ..
+            } => write!(f, "  ~ {} {} ({})", kind.name(), key, changed.join(", ")),
+            Change::Delete { kind, key, .. } => write!(f, "  - {} {}", kind.name(), key),
+        };
+    }
+}
+
+/// Changes needed to bring a store in line with a ``CatalogConfig``, created with ``SellAppClient::catalog_plan``.
+#[derive(Debug, Clone, Default)]
+pub struct Plan {
+    /// Changes in the order they are applied.
+    pub changes: Vec<Change>,
+}
+
+impl Plan {
+    pub fn is_empty(&self) -> bool {
+        return self.changes.is_empty();
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.changes.is_empty() {
+            return writeln!(f, "No changes, the store matches the config.");
+        }
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        }
+        let count = |check: fn(&Change) -> bool| self.changes.iter().filter(|c| check(c)).count();
+        return writeln!(
+            f,
+            "\nPlan: {} to create, {} to update, {} to delete.",
+            count(|change| matches!(change, Change::Create { .. })),
+            count(|change| matches!(change, Change::Update { .. })),
+            count(|change| matches!(change, Change::Delete { .. })),
+        );
+    }
+}
+
+/// Error returned while planning or applying a catalog config.
+#[derive(Debug)]
+pub enum CatalogError {
+    /// Calling the API failed.
+    Http(crate::Error),
+    /// The TOML config could not be parsed.
+    Toml(toml::de::Error),
+    /// The YAML config could not be parsed.
+    Yaml(serde_yaml::Error),
+    /// Reading the config failed.
+    Io(io::Error),
+    /// A created resource had no ID in the API response.
+    MissingId(String),
+    /// Several resources in the store have the same key, so it's unknown which one the config describes.
+    DuplicateKey { kind: CatalogKind, key: String },
+}
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            CatalogError::Http(error) => write!(f, "request failed: {}", error),
+            CatalogError::Toml(error) => write!(f, "invalid config: {}", error),
+            CatalogError::Yaml(error) => write!(f, "invalid config: {}", error),
+            CatalogError::Io(error) => write!(f, "failed to read config: {}", error),
+            CatalogError::MissingId(key) => write!(f, "no ID returned for created {}", key),
+            CatalogError::DuplicateKey { kind, key } => write!(
+                f,
+                "the store has several {} with the {} {:?}",
+                kind.name(),
+                kind.key_field(),
+                key
+            ),
+        };
+    }
+}
+
+impl std::error::Error for CatalogError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        return match self {
+            CatalogError::Http(error) => Some(error),
+            CatalogError::Toml(error) => Some(error),
+            CatalogError::Yaml(error) => Some(error),
+            CatalogError::Io(error) => Some(error),
+            CatalogError::MissingId(_) | CatalogError::DuplicateKey { .. } => None,
+        };
+    }
+}
+
+impl From<crate::Error> for CatalogError {
+    fn from(error: crate::Error) -> Self {
+        return CatalogError::Http(error);
+    }
+}
+
+impl From<reqwest::Error> for CatalogError {
+    fn from(error: reqwest::Error) -> Self {
+        return CatalogError::Http(error.into());
+    }
+}
+
+impl From<io::Error> for CatalogError {
+    fn from(error: io::Error) -> Self {
+        return CatalogError::Io(error);
+    }
+}
+
+impl From<toml::de::Error> for CatalogError {
+    fn from(error: toml::de::Error) -> Self {
+        return CatalogError::Toml(error);
+    }
+}
+
+impl From<serde_yaml::Error> for CatalogError {
+    fn from(error: serde_yaml::Error) -> Self {
+        return CatalogError::Yaml(error);
+    }
+}
+
+impl SellAppClient {
+    /// Compare ``config`` with the current state of the store, and plan the changes needed to match it.
+    ///
+    /// Resources are matched by their ``CatalogKind::key_field``, and only the attributes present in the config
+    /// are compared. Variants are matched within their product. Fails with ``CatalogError::DuplicateKey`` if
+    /// several resources of the store share a key.
+    pub async fn catalog_plan(&self, config: &CatalogConfig) -> Result<Plan, CatalogError> {
+        let client = self.uncached();
+        let mut plan = Plan::default();
+        let mut deletes = Vec::new();
+
+        let products = client.list_all_pages(Resource::Products, "").await?;
+        let products = by_key(CatalogKind::Products, products)?;
+        let specs: Vec<ResourceSpec> = config
+            .products
+            .iter()
+            .map(|product| product.spec.clone())
+            .collect();
+        diff(
+            CatalogKind::Products,
+            &specs,
+            &products,
+            None,
+            config.prune,
+            &mut plan.changes,
+            &mut deletes,
+        );
+
+        for product in &config.products {
+            match products.get(&product.spec.key) {
+                Some(existing) => {
+                    let product_id = resource_id(existing);
+                    let variants = client.variants_list_all_pages(product_id.clone()).await?;
+                    diff(
+                        CatalogKind::Variants,
+                        &product.variants,
+                        &by_key(CatalogKind::Variants, variants)?,
+                        Some((&product.spec.key, &product_id)),
+                        config.prune,
+                        &mut plan.changes,
+                        &mut deletes,
+                    );
+                }
+                None => {
+                    for variant in &product.variants {
+                        plan.changes.push(Change::Create {
+                            kind: CatalogKind::Variants,
+                            key: variant.key.clone(),
+                            parent: Some(product.spec.key.clone()),
+                            parent_id: None,
+                            data: spec_data(CatalogKind::Variants, variant),
+                        });
+                    }
+                }
+            }
+        }
+
+        let others = [
+            (CatalogKind::Groups, Resource::Groups, &config.groups),
+            (CatalogKind::Sections, Resource::Sections, &config.sections),
+            (CatalogKind::Coupons, Resource::Coupons, &config.coupons),
+            (
+                CatalogKind::Blacklists,
+                Resource::Blacklists,
+                &config.blacklists,
+            ),
+        ];
+        for (kind, resource, specs) in others {
+            let existing = client.list_all_pages(resource, "").await?;
+            diff(
+                kind,
+                specs,
+                &by_key(kind, existing)?,
+                None,
+                config.prune,
+                &mut plan.changes,
+                &mut deletes,
+            );
+        }
+
+        // Variants are deleted before their products.
+        deletes.sort_by_key(|change| match change {
+            Change::Delete {
+                kind: CatalogKind::Variants,
+                ..
+            } => 0,
+            _ => 1,
+        });
+        plan.changes.extend(deletes);
+        return Ok(plan);
+    }
+
+    /// Apply the changes of a plan in order, stopping at the first failure.
+    ///
+    /// ```no_run
+    /// use sellapp::catalog::CatalogConfig;
+    ///
+    /// # async fn run() -> Result<(), sellapp::catalog::CatalogError> {
+    /// let sellapp_api = sellapp::init("your_api_key", "");
+    /// let config = CatalogConfig::load("catalog.toml")?;
+    ///
+    /// let plan = sellapp_api.catalog_plan(&config).await?;
+    /// print!("{}", plan);
+    /// sellapp_api.catalog_apply(&plan).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn catalog_apply(&self, plan: &Plan) -> Result<(), CatalogError> {
+        // IDs of products created by this plan, by key, for creating their variants.
+        let mut created_products: HashMap<String, String> = HashMap::new();
+
+        for change in &plan.changes {
+            match change {
+                Change::Create {
+                    kind,
+                    key,
+                    parent,
+                    parent_id,
+                    ..
+                } => {
+                    let data = change_data(change);
+                    let res = match kind {
+                        CatalogKind::Products => self.products_create(data).await?,
+                        CatalogKind::Variants => {
+                            let parent = parent.clone().unwrap_or_default();
+                            let product_id = match (parent_id, created_products.get(&parent)) {
+                                (Some(id), _) | (None, Some(id)) => id.clone(),
+                                (None, None) => return Err(CatalogError::MissingId(parent)),
+                            };
+                            self.variants_create(product_id, data).await?
+                        }
+                        CatalogKind::Groups => self.groups_create(data).await?,
+                        CatalogKind::Sections => self.sections_create(data).await?,
+                        CatalogKind::Coupons => self.coupons_create(data).await?,
+                        CatalogKind::Blacklists => self.blacklist_create_rule(data).await?,
+                    };
+                    let created = unwrap_data(res.error_for_status()?.json::<Value>().await?);
+                    if *kind == CatalogKind::Products {
+                        let id = resource_id(&created);
+                        if id.is_empty() || id == "null" {
+                            return Err(CatalogError::MissingId(key.clone()));
+                        }
+                        created_products.insert(key.clone(), id);
+                    }
+                }
+                Change::Update {
+                    kind,
+                    id,
+                    parent_id,
+                    ..
+                } => {
+                    let id = id.clone();
+                    let data = change_data(change);
+                    let res = match kind {
+                        CatalogKind::Products => self.products_update(id, data).await?,
+                        CatalogKind::Variants => {
+                            let product_id = parent_id.clone().unwrap_or_default();
+                            self.variants_update(product_id, id, data).await?
+                        }
+                        CatalogKind::Groups => self.groups_update(id, data).await?,
+                        CatalogKind::Sections => self.sections_update(id, data).await?,
+                        CatalogKind::Coupons => self.coupons_update(id, data).await?,
+                        CatalogKind::Blacklists => self.blacklist_update_rule(id, data).await?,
+                    };
+                    res.error_for_status()?;
+                }
+                Change::Delete {
+                    kind,
+                    id,
+                    parent_id,
+                    ..
+                } => {
+                    let id = id.clone();
+                    let res = match kind {
+                        CatalogKind::Products => self.products_delete(id).await?,
+                        CatalogKind::Variants => {
+                            let product_id = parent_id.clone().unwrap_or_default();
+                            self.variants_delete(product_id, id).await?
+                        }
+                        CatalogKind::Groups => self.groups_delete(id).await?,
+                        CatalogKind::Sections => self.sections_delete(id).await?,
+                        CatalogKind::Coupons => self.coupons_delete(id).await?,
+                        CatalogKind::Blacklists => self.blacklist_delete_rule(id).await?,
+                    };
+                    res.error_for_status()?;
+                }
+            }
+        }
+        return Ok(());
+    }
+}
+
+/// Index existing resources by their key, failing if two share one.
+fn by_key(kind: CatalogKind, items: Vec<Value>) -> Result<HashMap<String, Value>, CatalogError> {
+    return util::by_key(items, kind.key_field())
+        .map_err(|key| CatalogError::DuplicateKey { kind, key });
+}
+
+/// Attributes of a spec, with its key set.
+fn spec_data(kind: CatalogKind, spec: &ResourceSpec) -> Value {
+    let mut data = spec.data.clone();
+    data.insert(
+        kind.key_field().to_string(),
+        Value::String(spec.key.clone()),
+    );
+    return Value::Object(data);
+}
+
+fn change_data(change: &Change) -> String {
+    return match change {
+        Change::Create { data, .. } | Change::Update { data, .. } => data.to_string(),
+        Change::Delete { .. } => String::new(),
+    };
+}
+
+/// Plan the changes for a single resource kind.
+fn diff(
+    kind: CatalogKind,
+    specs: &[ResourceSpec],
+    existing: &HashMap<String, Value>,
+    parent: Option<(&String, &String)>,
+    prune: bool,
+    changes: &mut Vec<Change>,
+    deletes: &mut Vec<Change>,
+) {
+    for spec in specs {
+        let data = spec_data(kind, spec);
+        let current = match existing.get(&spec.key) {
+            Some(current) => current,
+            None => {
+                changes.push(Change::Create {
+                    kind,
+                    key: spec.key.clone(),
+                    parent: parent.map(|(key, _)| key.clone()),
+                    parent_id: parent.map(|(_, id)| id.clone()),
+                    data,
+                });
+                continue;
+            }
+        };
+
+        let changed: Vec<String> = spec
+            .data
+            .iter()
+            .filter(|(field, value)| !json_equal(value, &current[field.as_str()]))
+            .map(|(field, _)| field.clone())
+            .collect();
+        if !changed.is_empty() {
+            changes.push(Change::Update {
+                kind,
+                key: spec.key.clone(),
+                id: resource_id(current),
+                parent_id: parent.map(|(_, id)| id.clone()),
+                changed,
+                data,
+            });
+        }
+    }
+
+    if prune {
+        for (key, current) in existing {
+            if !specs.iter().any(|spec| spec.key == *key) {
+                deletes.push(Change::Delete {
+                    kind,
+                    key: key.clone(),
+                    id: resource_id(current),
+                    parent_id: parent.map(|(_, id)| id.clone()),
+                });
+            }
+        }
+    }
+}
+
+/// Compare a configured value with the API value, treating numbers and numeric strings as equal.
+///
+/// API values that are ``Money`` objects are compared as ``Money``, with plain configured amounts taken in the
+/// currency of the API value.
+fn json_equal(wanted: &Value, current: &Value) -> bool {
+    if let Ok(current) = Money::from_value(current) {
+        let wanted = match json_string(wanted) {
+            Some(amount) => Money::from_major_str(&amount, current.currency),
+            None => Money::from_value(wanted),
+        };
+        return wanted == Ok(current);
+    }
+    return match (json_string(wanted), json_string(current)) {
+        (Some(wanted), Some(current)) => wanted == current,
+        _ => wanted == current,
+    };
+}
+
+fn json_string(value: &Value) -> Option<String> {
+    return match value {
+        Value::String(value) => Some(value.clone()),
+        Value::Number(value) => Some(value.to_string()),
+        Value::Bool(value) => Some(value.to_string()),
+        _ => None,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn spec(key: &str, data: Value) -> ResourceSpec {
+        return ResourceSpec {
+            key: key.to_string(),
+            data: data.as_object().unwrap().clone(),
+        };
+    }
+
+    fn plan(
+        specs: &[ResourceSpec],
+        existing: Vec<Value>,
+        prune: bool,
+    ) -> (Vec<Change>, Vec<Change>) {
+        let (mut changes, mut deletes) = (Vec::new(), Vec::new());
+        let existing = by_key(CatalogKind::Variants, existing).unwrap();
+        let parent = ("steam-key".to_string(), "7".to_string());
+        diff(
+            CatalogKind::Variants,
+            specs,
+            &existing,
+            Some((&parent.0, &parent.1)),
+            prune,
+            &mut changes,
+            &mut deletes,
+        );
+        return (changes, deletes);
+    }
+
+    #[test]
+    fn plans_creates_updates_and_deletes() {
+        let specs = [
+            spec("Single key", json!({ "description": "One key" })),
+            spec("Bundle", json!({ "description": "Five keys" })),
+        ];
+        let existing = vec![
+            json!({ "id": 1, "title": "Bundle", "description": "Three keys" }),
+            json!({ "id": 2, "title": "Old", "description": "Gone" }),
+        ];
+
+        let (changes, deletes) = plan(&specs, existing.clone(), true);
+        assert_eq!(changes.len(), 2);
+        match &changes[0] {
+            Change::Create {
+                key,
+                parent,
+                parent_id,
+                data,
+                ..
+            } => {
+                assert_eq!(key, "Single key");
+                assert_eq!(parent.as_deref(), Some("steam-key"));
+                assert_eq!(parent_id.as_deref(), Some("7"));
+                assert_eq!(
+                    data,
+                    &json!({ "title": "Single key", "description": "One key" })
+                );
+            }
+            change => panic!("unexpected change: {:?}", change),
+        }
+        match &changes[1] {
+            Change::Update {
+                key, id, changed, ..
+            } => {
+                assert_eq!((key.as_str(), id.as_str()), ("Bundle", "1"));
+                assert_eq!(changed, &["description"]);
+            }
+            change => panic!("unexpected change: {:?}", change),
+        }
+        match &deletes[..] {
+            [Change::Delete { key, id, .. }] => {
+                assert_eq!((key.as_str(), id.as_str()), ("Old", "2"))
+            }
+            deletes => panic!("unexpected deletes: {:?}", deletes),
+        }
+
+        let (_, deletes) = plan(&specs, existing, false);
+        assert!(deletes.is_empty());
+    }
+
+    #[test]
+    fn plans_nothing_when_the_store_matches() {
+        let specs = [spec(
+            "Single key",
+            json!({ "price": "4.99", "stock": 10, "active": true, "description": "One key" }),
+        )];
+        let existing = vec![json!({
+            "id": 1,
+            "title": "Single key",
+            "price": { "price": "4.990", "currency": "USD" },
+            "stock": "10",
+            "active": true,
+            "description": "One key",
+            "created_at": "2026-01-01T00:00:00Z",
+        })];
+        assert!(plan(&specs, existing, true).0.is_empty());
+
+        let existing = vec![
+            json!({ "id": 1, "title": "Single key", "price": { "base": 499, "currency": "EUR" } }),
+        ];
+        let specs = [spec("Single key", json!({ "price": 4.99 }))];
+        assert!(plan(&specs, existing, false).0.is_empty());
+    }
+
+    #[test]
+    fn compares_prices_as_money() {
+        let current = json!({ "price": "4.99", "currency": "USD" });
+        assert!(json_equal(&json!("4.99"), &current));
+        assert!(json_equal(
+            &json!({ "base": 499, "currency": "USD" }),
+            &current
+        ));
+        assert!(!json_equal(&json!("5.00"), &current));
+        assert!(!json_equal(
+            &json!({ "price": "4.99", "currency": "EUR" }),
+            &current
+        ));
+        assert!(!json_equal(&json!("free"), &current));
+    }
+
+    #[test]
+    fn rejects_duplicate_keys() {
+        let existing = vec![
+            json!({ "id": 1, "slug": "steam-key" }),
+            json!({ "id": 2, "slug": "steam-key" }),
+        ];
+        match by_key(CatalogKind::Products, existing) {
+            Err(CatalogError::DuplicateKey { kind, key }) => {
+                assert_eq!((kind, key.as_str()), (CatalogKind::Products, "steam-key"));
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn keeps_parse_errors() {
+        let error = CatalogConfig::from_toml_str("prune = maybe").unwrap_err();
+        assert!(matches!(error, CatalogError::Toml(_)));
+        assert!(std::error::Error::source(&error).is_some());
+        let error = CatalogConfig::from_yaml_str("products: 12").unwrap_err();
+        assert!(matches!(error, CatalogError::Yaml(_)));
+    }
+}
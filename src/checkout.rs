@@ -0,0 +1,290 @@
+use std::{fmt, time::Duration};
+
+use crate::Error;
+use serde_json::{json, Value};
+
+use crate::{
+    rt::{sleep, Instant},
+    util::{resource_id, unwrap_data},
+    SellAppClient,
+};
+
+const POLL_INTERVAL_MIN: Duration = Duration::from_secs(2);
+const POLL_INTERVAL_MAX: Duration = Duration::from_secs(30);
+
+/// A single product variant to be bought.
+#[derive(Debug, Clone)]
+pub struct CartLine {
+    pub product_id: String,
+    pub variant_id: String,
+    pub quantity: u32,
+}
+
+/// Everything needed to create an invoice for a customer.
+#[derive(Debug, Clone)]
+pub struct Cart {
+    /// Email address of the customer, the goods are delivered there.
+    pub email: String,
+    /// Payment method the customer pays with, e.g. **"STRIPE"** or **"PAYPAL"**.
+    pub payment_method: String,
+    pub lines: Vec<CartLine>,
+    /// Optional coupon code to apply to the invoice.
+    pub coupon: Option<String>,
+}
+
+impl Cart {
+    /// JSON body for ``SellAppClient::invoices_create``.
+    pub fn to_invoice_data(&self) -> Value {
+        let lines: Vec<Value> = self
+            .lines
+            .iter()
+            .map(|line| {
+                json!({
+                    "product_id": line.product_id,
+                    "variant_id": line.variant_id,
+                    "quantity": line.quantity,
+                })
+            })
+            .collect();
+        let mut data = json!({
+            "customer_information": { "email": self.email },
+            "payment_method": self.payment_method,
+            "lines": lines,
+        });
+        if let Some(coupon) = &self.coupon {
+            data["coupon_code"] = json!(coupon);
+        }
+        return data;
+    }
+}
+
+/// How a checkout session ended, see ``CheckoutSession::wait_for_completion``.
+#[derive(Debug, Clone)]
+pub enum CheckoutOutcome {
+    /// The invoice was paid. ``deliverables`` is the response of ``SellAppClient::invoices_get_items``.
+    Completed { invoice: Value, deliverables: Value },
+    /// The invoice was voided before it was paid.
+    Voided(Value),
+    /// The invoice was still pending when the timeout ran out.
+    Expired(Value),
+}
+
+/// Error returned by ``SellAppClient::checkout_start``.
+#[derive(Debug)]
+pub enum CheckoutError {
+    /// Calling the API failed.
+    Http(Error),
+    /// The checkout of the invoice was started, but the response has no payment URL to send the customer to.
+    MissingPaymentUrl { invoice_id: String },
+}
+
+impl fmt::Display for CheckoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            CheckoutError::Http(error) => write!(f, "request failed: {}", error),
+            CheckoutError::MissingPaymentUrl { invoice_id } => write!(
+                f,
+                "the checkout of invoice {} has no payment URL",
+                invoice_id
+            ),
+        };
+    }
+}
+
+impl std::error::Error for CheckoutError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        return match self {
+            CheckoutError::Http(error) => Some(error),
+            CheckoutError::MissingPaymentUrl { .. } => None,
+        };
+    }
+}
+
+impl From<Error> for CheckoutError {
+    fn from(error: Error) -> Self {
+        return CheckoutError::Http(error);
+    }
+}
+
+impl From<reqwest::Error> for CheckoutError {
+    fn from(error: reqwest::Error) -> Self {
+        return CheckoutError::Http(error.into());
+    }
+}
+
+/// An invoice with a started checkout, created by ``SellAppClient::checkout_start``.
+pub struct CheckoutSession<'a> {
+    client: &'a SellAppClient,
+    pub invoice_id: String,
+    /// The invoice as returned when it was created.
+    pub invoice: Value,
+    /// URL the customer has to visit to pay for the invoice.
+    pub payment_url: String,
+}
+
+impl SellAppClient {
+    /// Create an invoice from ``cart`` and start a checkout session for it.
+    ///
+    /// The returned session holds the payment URL to send the customer to. If the checkout response has none,
+    /// ``CheckoutError::MissingPaymentUrl`` is returned instead.
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use sellapp::checkout::{Cart, CartLine, CheckoutOutcome};
+    ///
+    /// # async fn run() -> Result<(), sellapp::checkout::CheckoutError> {
+    /// let sellapp_api = sellapp::init("your_api_key", "");
+    /// let cart = Cart {
+    ///     email: "customer@example.com".to_string(),
+    ///     payment_method: "STRIPE".to_string(),
+    ///     lines: vec![CartLine { product_id: "1".to_string(), variant_id: "2".to_string(), quantity: 1 }],
+    ///     coupon: None,
+    /// };
+    ///
+    /// let session = sellapp_api.checkout_start(&cart).await?;
+    /// println!("Pay here: {}", session.payment_url);
+    ///
+    /// if let CheckoutOutcome::Completed { deliverables, .. } =
+    ///     session.wait_for_completion(Duration::from_secs(900)).await?
+    /// {
+    ///     println!("Delivered: {}", deliverables);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn checkout_start(&self, cart: &Cart) -> Result<CheckoutSession<'_>, CheckoutError> {
+        let created = self
+            .invoices_create(cart.to_invoice_data().to_string())
+            .await?
+            .error_for_status()?;
+        let invoice = unwrap_data(created.json::<Value>().await?);
+        let invoice_id = resource_id(&invoice);
+
+        let checkout = self
+            .invoices_checkout(invoice_id.clone())
+            .await?
+            .error_for_status()?;
+        let checkout = unwrap_data(checkout.json::<Value>().await?);
+        let payment_url = match ["url", "payment_url", "checkout_url"]
+            .iter()
+            .find_map(|key| checkout[*key].as_str().filter(|url| !url.is_empty()))
+        {
+            Some(payment_url) => payment_url.to_string(),
+            None => return Err(CheckoutError::MissingPaymentUrl { invoice_id }),
+        };
+
+        return Ok(CheckoutSession {
+            client: self,
+            invoice_id,
+            invoice,
+            payment_url,
+        });
+    }
+}
+
+impl CheckoutSession<'_> {
+    /// Poll the invoice until it is completed or voided, or until ``timeout`` runs out.
+    ///
+    /// Polling starts every 2 seconds and backs off up to every 30 seconds. Once the invoice is completed,
+    /// its deliverables are fetched as well.
+    pub async fn wait_for_completion(&self, timeout: Duration) -> Result<CheckoutOutcome, Error> {
+        let client = self.client.uncached();
+        let deadline = Instant::now() + timeout;
+        let mut interval = POLL_INTERVAL_MIN;
+
+        loop {
+            let res = client
+                .invoices_get(self.invoice_id.clone())
+                .await?
+                .error_for_status()?;
+            let invoice = unwrap_data(res.json::<Value>().await?);
+
+            match invoice_status(&invoice).as_deref() {
+                Some("COMPLETED") => {
+                    let deliverables = client
+                        .invoices_get_items(self.invoice_id.clone())
+                        .await?
+                        .error_for_status()?
+                        .json::<Value>()
+                        .await?;
+                    return Ok(CheckoutOutcome::Completed {
+                        invoice,
+                        deliverables,
+                    });
+                }
+                Some("VOIDED") => return Ok(CheckoutOutcome::Voided(invoice)),
+                _ => {}
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(CheckoutOutcome::Expired(invoice));
+            }
+            sleep(interval.min(deadline - now)).await;
+            interval = (interval * 2).min(POLL_INTERVAL_MAX);
+        }
+    }
+}
+
+/// Get the current status of an invoice in uppercase, e.g. **"PENDING"** or **"COMPLETED"**.
+///
+/// Accepts both a plain ``status`` string and the nested ``status.status.status`` object of v2 invoices.
+pub fn invoice_status(invoice: &Value) -> Option<String> {
+    let mut status = &invoice["status"];
+    while let Some(nested) = status.get("status") {
+        status = nested;
+    }
+    return status.as_str().map(|status| status.to_uppercase());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::{self, MockTransport};
+
+    fn cart() -> Cart {
+        return Cart {
+            email: "customer@example.com".to_string(),
+            payment_method: "STRIPE".to_string(),
+            lines: vec![CartLine {
+                product_id: "1".to_string(),
+                variant_id: "2".to_string(),
+                quantity: 1,
+            }],
+            coupon: None,
+        };
+    }
+
+    /// A store answering checkouts with ``checkout``.
+    fn store(checkout: Value) -> MockTransport {
+        return MockTransport::new(move |line, _| {
+            return match line {
+                "POST v2/invoices" => mock::json(201, json!({ "data": { "id": 7 } })),
+                _ => mock::json(200, json!({ "data": checkout })),
+            };
+        });
+    }
+
+    #[tokio::test]
+    async fn starts_a_checkout() {
+        let transport = store(json!({ "payment_url": "https://pay.example.com/7" }));
+        let client = mock::client(&transport);
+        let session = client.checkout_start(&cart()).await.unwrap();
+        assert_eq!(session.invoice_id, "7");
+        assert_eq!(session.payment_url, "https://pay.example.com/7");
+        assert_eq!(transport.requests()[1], "POST v2/invoices/7/checkout");
+    }
+
+    #[tokio::test]
+    async fn fails_without_a_payment_url() {
+        for checkout in [json!({}), json!({ "url": "" }), json!({ "url": null })] {
+            let transport = store(checkout);
+            let client = mock::client(&transport);
+            match client.checkout_start(&cart()).await {
+                Err(CheckoutError::MissingPaymentUrl { invoice_id }) => assert_eq!(invoice_id, "7"),
+                Err(error) => panic!("unexpected error: {}", error),
+                Ok(session) => panic!("unexpected payment URL {:?}", session.payment_url),
+            }
+        }
+    }
+}
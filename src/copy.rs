@@ -0,0 +1,412 @@
+use std::collections::{HashMap, HashSet};
+
+use reqwest::Response;
+
+use crate::Error;
+use serde_json::{json, Value};
+
+use crate::{
+    resource::Resource,
+    util::{by_key, check_response, creatable, id_string, resource_id},
+    SellAppClient,
+};
+
+/// Why items are not copied when their key is ambiguous in the destination store.
+const DUPLICATE_KEY: &str = "several items in the destination store have this key";
+
+/// An item that could not be copied.
+#[derive(Debug, Clone)]
+pub struct CopyFailure {
+    /// Resource type of the item, e.g. **"products"**.
+    pub resource: String,
+    /// Stable key of the item (slug or title).
+    pub key: String,
+    pub reason: String,
+}
+
+/// Outcome of ``SellAppClient::copy_catalog``.
+#[derive(Debug, Clone, Default)]
+pub struct CopyReport {
+    /// Amount of items created in the destination store.
+    pub created: usize,
+    /// Amount of items that already existed in the destination store and were updated.
+    pub updated: usize,
+    /// Amount of products added to groups in the destination store.
+    pub attached: usize,
+    pub failed: Vec<CopyFailure>,
+}
+
+impl CopyReport {
+    fn fail(&mut self, resource: &str, key: &str, reason: String) {
+        self.failed.push(CopyFailure {
+            resource: resource.to_string(),
+            key: key.to_string(),
+            reason,
+        });
+    }
+}
+
+impl SellAppClient {
+    /// Copy products with their variants, group memberships and sections from one store to another.
+    ///
+    /// Products are matched by ``slug``, and variants, groups and sections by ``title``. Items that already exist in the
+    /// destination store are updated instead of created, so copying the same catalog again is safe. Groups and sections
+    /// are only copied if one of the selected products belongs to them.
+    ///
+    /// Serial keys are never copied, so variants that already exist in the destination store keep their stock.
+    ///
+    /// If several sections, products or groups of the destination store share a key, the copy stops before that
+    /// resource, as it's unknown which of them to update, and the key is reported as failed. Variants sharing a title
+    /// only skip the variants of their product.
+    ///
+    /// ``from_store``: The slug of the store to copy from, required.
+    ///
+    /// ``to_store``: The slug of the store to copy to, required.
+    ///
+    /// ``filter``: Selects the products to copy, called with the product JSON of the source store.
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), sellapp::Error> {
+    /// let sellapp_api = sellapp::init("your_api_key", "");
+    /// let report = sellapp_api
+    ///     .copy_catalog("my-staging-store", "my-store", |product| product["visibility"] == "PUBLIC")
+    ///     .await?;
+    /// println!("{} created, {} updated, {} failed", report.created, report.updated, report.failed.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn copy_catalog(
+        &self,
+        from_store: &str,
+        to_store: &str,
+        filter: impl Fn(&Value) -> bool,
+    ) -> Result<CopyReport, Error> {
+        let source = self.uncached().for_store(from_store);
+        let target = self.uncached().for_store(to_store);
+        let mut report = CopyReport::default();
+
+        let products: Vec<Value> = source
+            .list_all_pages(Resource::Products, "")
+            .await?
+            .into_iter()
+            .filter(|product| filter(product))
+            .collect();
+        let product_ids: HashSet<String> = products.iter().map(resource_id).collect();
+
+        // Sections used by the selected products, by ID in the source store.
+        let mut section_ids = HashMap::new();
+        let target_sections = match by_key(
+            target.list_all_pages(Resource::Sections, "").await?,
+            "title",
+        ) {
+            Ok(sections) => sections,
+            Err(key) => {
+                report.fail("sections", &key, DUPLICATE_KEY.to_string());
+                return Ok(report);
+            }
+        };
+        for section in source.list_all_pages(Resource::Sections, "").await? {
+            let used = products
+                .iter()
+                .any(|product| id_string(&product["section_id"]) == Some(resource_id(&section)));
+            if !used {
+                continue;
+            }
+            let title = section["title"].as_str().unwrap_or_default().to_string();
+            let data = creatable(&section).to_string();
+            let existing = target_sections.get(&title).map(resource_id);
+            let res = match &existing {
+                Some(id) => target.sections_update(id.clone(), data).await,
+                None => target.sections_create(data).await,
+            };
+            if let Some(id) = finish(&mut report, "sections", &title, existing, res).await {
+                section_ids.insert(resource_id(&section), id);
+            }
+        }
+
+        // IDs of the copied products in the destination store, by ID in the source store.
+        let mut copied = HashMap::new();
+        let target_products =
+            match by_key(target.list_all_pages(Resource::Products, "").await?, "slug") {
+                Ok(products) => products,
+                Err(key) => {
+                    report.fail("products", &key, DUPLICATE_KEY.to_string());
+                    return Ok(report);
+                }
+            };
+        for product in &products {
+            let slug = product["slug"].as_str().unwrap_or_default().to_string();
+            let mut data = creatable(product);
+            if let Some(section_id) = id_string(&data["section_id"]) {
+                data["section_id"] = json!(section_ids.get(&section_id));
+            }
+            let existing = target_products.get(&slug).map(resource_id);
+            let exists = existing.is_some();
+            let res = match &existing {
+                Some(id) => target.products_update(id.clone(), data.to_string()).await,
+                None => target.products_create(data.to_string()).await,
+            };
+            let target_id = match finish(&mut report, "products", &slug, existing, res).await {
+                Some(id) => id,
+                None => continue,
+            };
+            copied.insert(resource_id(product), target_id.clone());
+
+            let target_variants = match exists {
+                true => by_key(
+                    target.variants_list_all_pages(target_id.clone()).await?,
+                    "title",
+                ),
+                false => Ok(HashMap::new()),
+            };
+            let target_variants = match target_variants {
+                Ok(variants) => variants,
+                Err(title) => {
+                    let key = format!("{}/{}", slug, title);
+                    report.fail("variants", &key, DUPLICATE_KEY.to_string());
+                    continue;
+                }
+            };
+            for variant in source.variants_list_all_pages(resource_id(product)).await? {
+                let title = variant["title"].as_str().unwrap_or_default().to_string();
+                let mut data = creatable(&variant);
+                if let Some(deliverable) = data["deliverable"]["data"].as_object_mut() {
+                    deliverable.remove("serials");
+                }
+                let data = data.to_string();
+                let existing = target_variants.get(&title).map(resource_id);
+                let res = match &existing {
+                    Some(id) => {
+                        target
+                            .variants_update(target_id.clone(), id.clone(), data)
+                            .await
+                    }
+                    None => target.variants_create(target_id.clone(), data).await,
+                };
+                let key = format!("{}/{}", slug, title);
+                finish(&mut report, "variants", &key, existing, res).await;
+            }
+        }
+
+        let target_groups =
+            match by_key(target.list_all_pages(Resource::Groups, "").await?, "title") {
+                Ok(groups) => groups,
+                Err(key) => {
+                    report.fail("groups", &key, DUPLICATE_KEY.to_string());
+                    return Ok(report);
+                }
+            };
+        for group in source.list_all_pages(Resource::Groups, "").await? {
+            let members: Vec<String> = source
+                .groups_list_products_all_pages(resource_id(&group))
+                .await?
+                .iter()
+                .map(resource_id)
+                .filter(|id| product_ids.contains(id))
+                .collect();
+            if members.is_empty() {
+                continue;
+            }
+
+            let title = group["title"].as_str().unwrap_or_default().to_string();
+            let existing = target_groups.get(&title).map(resource_id);
+            let exists = existing.is_some();
+            let data = creatable(&group).to_string();
+            let res = match &existing {
+                Some(id) => target.groups_update(id.clone(), data).await,
+                None => target.groups_create(data).await,
+            };
+            let target_group_id = match finish(&mut report, "groups", &title, existing, res).await {
+                Some(id) => id,
+                None => continue,
+            };
+
+            let current: HashSet<String> = match exists {
+                true => target
+                    .groups_list_products_all_pages(target_group_id.clone())
+                    .await?
+                    .iter()
+                    .map(resource_id)
+                    .collect(),
+                false => HashSet::new(),
+            };
+            let missing: Vec<&String> = members
+                .iter()
+                .filter_map(|id| copied.get(id))
+                .filter(|id| !current.contains(*id))
+                .collect();
+            if missing.is_empty() {
+                continue;
+            }
+            let data = json!({ "products": missing }).to_string();
+            let res = target.groups_add_products(target_group_id, data).await;
+            match check_response(res).await {
+                Ok(_) => report.attached += missing.len(),
+                Err(reason) => report.fail("group_products", &title, reason),
+            }
+        }
+
+        return Ok(report);
+    }
+}
+
+/// Count a create or update call, and get the ID of the item in the destination store.
+///
+/// ``existing``: The ID of the item if it was updated, ``None`` if it was created.
+///
+/// A created item whose response has no ID is counted, but also reported as failed, as the items depending on it
+/// can't be copied.
+async fn finish(
+    report: &mut CopyReport,
+    resource: &str,
+    key: &str,
+    existing: Option<String>,
+    res: Result<Response, Error>,
+) -> Option<String> {
+    match check_response(res).await {
+        Ok(item) => {
+            let id = match existing {
+                Some(existing) => {
+                    report.updated += 1;
+                    existing
+                }
+                None => {
+                    report.created += 1;
+                    resource_id(&item)
+                }
+            };
+            if id.is_empty() || id == "null" {
+                let reason = "created, but the API response has no ID".to_string();
+                report.fail(resource, key, reason);
+                return None;
+            }
+            return Some(id);
+        }
+        Err(reason) => {
+            report.fail(resource, key, reason);
+            return None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::transport::mock::{self, MockTransport};
+
+    /// A staging store with two products in a section and a group, and a live store that already has the section,
+    /// one of the products with a variant, and the group with that product in it. Items created in the live store get
+    /// the IDs 100, 101, ...
+    fn stores() -> MockTransport {
+        let next_id = AtomicUsize::new(100);
+        return MockTransport::per_store(move |store, line, _| {
+            let path = line.split('?').next().unwrap();
+            let data = match (store, path) {
+                ("staging", "GET v1/sections") => json!([{ "id": 1, "title": "Games" }]),
+                ("staging", "GET v2/products") => json!([
+                    { "id": 2, "slug": "steam-key", "section_id": 1, "visibility": "PUBLIC" },
+                    { "id": 3, "slug": "origin-key", "section_id": "1", "visibility": "PUBLIC" },
+                    { "id": 4, "slug": "draft", "section_id": 1, "visibility": "HIDDEN" },
+                ]),
+                ("staging", "GET v2/products/2/variants") => json!([{
+                    "id": 5,
+                    "title": "Single",
+                    "deliverable": { "data": { "serials": ["A"], "removeOnSale": true } },
+                }]),
+                ("staging", "GET v2/products/3/variants") => {
+                    json!([{ "id": 6, "title": "Single" }])
+                }
+                ("staging", "GET v2/groups") => json!([
+                    { "id": 7, "title": "Bundles" },
+                    { "id": 8, "title": "Drafts" },
+                ]),
+                ("staging", "GET v2/groups/7/products") => json!([{ "id": 2 }, { "id": 3 }]),
+                ("staging", "GET v2/groups/8/products") => json!([{ "id": 4 }]),
+                ("live", "GET v1/sections") => json!([{ "id": 11, "title": "Games" }]),
+                ("live", "GET v2/products") => json!([{ "id": 12, "slug": "steam-key" }]),
+                ("live", "GET v2/products/12/variants") => json!([{ "id": 15, "title": "Single" }]),
+                ("live", "GET v2/groups") => json!([{ "id": 17, "title": "Bundles" }]),
+                ("live", "GET v2/groups/17/products") => json!([{ "id": 12 }]),
+                ("live", _) if line.starts_with("POST") => {
+                    let id = next_id.fetch_add(1, Ordering::SeqCst);
+                    json!({ "id": id })
+                }
+                _ => json!([]),
+            };
+            return mock::json(200, json!({ "data": data }));
+        });
+    }
+
+    #[tokio::test]
+    async fn copies_selected_products_into_existing_items() {
+        let transport = stores();
+        let report = mock::client(&transport)
+            .copy_catalog("staging", "live", |product| {
+                product["visibility"] == "PUBLIC"
+            })
+            .await
+            .unwrap();
+        assert!(report.failed.is_empty(), "{:?}", report.failed);
+        assert_eq!((report.created, report.updated, report.attached), (2, 4, 1));
+
+        // Items with a matching key are updated in place, the others are created.
+        assert_eq!(transport.count("PATCH v1/sections/11"), 1);
+        assert_eq!(transport.count("POST v1/sections"), 0);
+        assert_eq!(transport.count("PATCH v2/products/12/variants/15"), 1);
+        assert_eq!(transport.count("POST v2/products/100/variants"), 1);
+        assert_eq!(transport.count("PATCH v2/groups/17"), 1);
+        assert!(!transport.requests().contains(&"POST v2/groups".to_string()));
+
+        // References are remapped to the IDs of the destination store, and serial keys are left out.
+        let updated = &transport.bodies("PATCH v2/products/12")[0];
+        assert_eq!(updated["section_id"], "11");
+        assert_eq!(updated.get("id"), None);
+        let created = &transport.bodies("POST v2/products")[0];
+        assert_eq!(
+            (&created["slug"], &created["section_id"]),
+            (&json!("origin-key"), &json!("11"))
+        );
+        let variant = &transport.bodies("PATCH v2/products/12/variants/15")[0];
+        assert_eq!(
+            variant["deliverable"]["data"],
+            json!({ "removeOnSale": true })
+        );
+
+        // Only the copied product missing from the group is attached, and the group of unselected products is left out.
+        let attached = transport.bodies("POST v2/groups/17/products/attach");
+        assert_eq!(attached, [json!({ "products": ["100"] })]);
+        assert_eq!(transport.count("PATCH v2/groups/8"), 0);
+    }
+
+    #[tokio::test]
+    async fn stops_on_ambiguous_keys_in_the_destination() {
+        let transport = MockTransport::per_store(|store, line, _| {
+            let data = match (store, line.split('?').next().unwrap()) {
+                ("staging", "GET v2/products") => json!([{ "id": 2, "slug": "steam-key" }]),
+                ("live", "GET v2/products") => json!([
+                    { "id": 12, "slug": "steam-key" },
+                    { "id": 13, "slug": "steam-key" },
+                ]),
+                _ => json!([]),
+            };
+            return mock::json(200, json!({ "data": data }));
+        });
+        let report = mock::client(&transport)
+            .copy_catalog("staging", "live", |_| true)
+            .await
+            .unwrap();
+
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(
+            (
+                report.failed[0].resource.as_str(),
+                report.failed[0].key.as_str()
+            ),
+            ("products", "steam-key")
+        );
+        assert_eq!(transport.count("PATCH"), 0);
+        assert_eq!(transport.count("POST"), 0);
+    }
+}
@@ -0,0 +1,671 @@
+use crate::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{util::unwrap_data, SellAppClient};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    fmt,
+    fs::{self, OpenOptions},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use futures::{AsyncWrite, AsyncWriteExt};
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::{
+    header::{CONTENT_RANGE, RANGE},
+    Method, Response, StatusCode, Url,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use sha2::{Digest, Sha256};
+
+/// Goods delivered to the customer for an invoice, see ``SellAppClient::invoices_get_deliverables``.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Deliverable {
+    /// Serial keys, one per bought unit.
+    Serials(Vec<String>),
+    /// Fixed text, e.g. instructions or a download link.
+    Text(String),
+    /// A downloadable file.
+    File(FileDeliverable),
+    /// The response of a dynamic delivery webhook.
+    Dynamic(Value),
+    /// A deliverable of a kind this crate doesn't know yet.
+    Other(Value),
+}
+
+/// A downloadable file, see ``SellAppClient::download_deliverable``.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileDeliverable {
+    pub url: String,
+    /// Original file name, if known.
+    pub name: Option<String>,
+    /// Size in bytes, if known.
+    pub size: Option<u64>,
+    /// Hex SHA-256 checksum of the file, if known.
+    pub sha256: Option<String>,
+}
+
+impl Deliverable {
+    /// Read the deliverables of an ``invoices_get_items`` response body, or of any part of it.
+    ///
+    /// Each item may hold several kinds at once, e.g. ``{"data": {"serials": [...], "text": "..."}}``.
+    /// Serials sent as a single string are split by line.
+    pub fn parse_all(body: &Value) -> Vec<Deliverable> {
+        let mut deliverables = Vec::new();
+        let body = match &body["data"] {
+            Value::Array(_) => &body["data"],
+            _ => body,
+        };
+        match unwrap_data(body.clone()) {
+            Value::Array(items) => items
+                .iter()
+                .for_each(|item| parse_item(item, &mut deliverables)),
+            Value::Null => {}
+            item => parse_item(&item, &mut deliverables),
+        }
+        return deliverables;
+    }
+}
+
+fn parse_item(item: &Value, deliverables: &mut Vec<Deliverable>) {
+    let data = if item["data"].is_object() {
+        &item["data"]
+    } else {
+        item
+    };
+    let count = deliverables.len();
+
+    match &data["serials"] {
+        Value::Array(serials) => deliverables.push(Deliverable::Serials(
+            serials
+                .iter()
+                .filter_map(|serial| serial.as_str().map(str::to_string))
+                .collect(),
+        )),
+        Value::String(serials) => deliverables.push(Deliverable::Serials(
+            serials
+                .lines()
+                .map(str::trim)
+                .filter(|serial| !serial.is_empty())
+                .map(str::to_string)
+                .collect(),
+        )),
+        _ => {}
+    }
+    if let Some(text) = data["text"].as_str() {
+        deliverables.push(Deliverable::Text(text.to_string()));
+    }
+    let files = match (&data["file"], &data["files"]) {
+        (Value::Object(_), _) => vec![&data["file"]],
+        (_, Value::Array(files)) => files.iter().collect(),
+        _ => Vec::new(),
+    };
+    for file in files {
+        match parse_file(file) {
+            Some(file) => deliverables.push(Deliverable::File(file)),
+            None => deliverables.push(Deliverable::Other(file.clone())),
+        }
+    }
+    if !data["dynamic"].is_null() {
+        deliverables.push(Deliverable::Dynamic(data["dynamic"].clone()));
+    }
+
+    if deliverables.len() == count && !item.is_null() {
+        deliverables.push(Deliverable::Other(item.clone()));
+    }
+}
+
+fn parse_file(file: &Value) -> Option<FileDeliverable> {
+    let first_str = |fields: &[&str]| {
+        fields
+            .iter()
+            .find_map(|field| file[*field].as_str())
+            .map(str::to_string)
+    };
+    let size = match &file["size"] {
+        Value::Number(size) => size.as_u64(),
+        Value::String(size) => size.parse().ok(),
+        _ => None,
+    };
+    let sha256 = first_str(&["sha256", "checksum"]).map(|checksum| {
+        let checksum = checksum.strip_prefix("sha256:").unwrap_or(&checksum);
+        checksum.to_ascii_lowercase()
+    });
+    return Some(FileDeliverable {
+        url: first_str(&["url", "download_url", "link"])?,
+        name: first_str(&["name", "filename", "original_name"]),
+        size,
+        sha256,
+    });
+}
+
+/// Outcome of downloading a file deliverable.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct Download {
+    /// Where the file was written, for downloads to a directory.
+    pub path: Option<PathBuf>,
+    /// Size of the complete file in bytes.
+    pub size: u64,
+    /// Amount of bytes that were already there and not downloaded again.
+    pub resumed_from: u64,
+    /// Whether the SHA-256 checksum of the file was checked.
+    pub verified: bool,
+}
+
+/// Error returned while downloading a file deliverable.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub enum DownloadError {
+    /// The download request failed.
+    Http(crate::Error),
+    /// Writing the file failed.
+    Io(io::Error),
+    /// The downloaded file doesn't have the advertised size.
+    SizeMismatch { expected: u64, actual: u64 },
+    /// The downloaded file doesn't have the advertised checksum. The partial file is removed.
+    ChecksumMismatch { expected: String, actual: String },
+    /// The server answered a resumed download with another range than the one requested.
+    RangeMismatch {
+        requested: u64,
+        content_range: Option<String>,
+    },
+    /// The URL of the file is neither absolute nor a valid path on Sell.App.
+    InvalidUrl(String),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            DownloadError::Http(error) => write!(f, "download failed: {}", error),
+            DownloadError::Io(error) => write!(f, "failed to write download: {}", error),
+            DownloadError::SizeMismatch { expected, actual } => write!(
+                f,
+                "downloaded {} bytes, expected {} bytes",
+                actual, expected
+            ),
+            DownloadError::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "checksum mismatch: expected sha256 {}, got {}",
+                expected, actual
+            ),
+            DownloadError::RangeMismatch {
+                requested,
+                content_range,
+            } => write!(
+                f,
+                "requested bytes from {} on, got range {}",
+                requested,
+                content_range.as_deref().unwrap_or("none")
+            ),
+            DownloadError::InvalidUrl(url) => write!(f, "invalid file URL: {}", url),
+        };
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl std::error::Error for DownloadError {}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<crate::Error> for DownloadError {
+    fn from(error: crate::Error) -> Self {
+        return DownloadError::Http(error);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<reqwest::Error> for DownloadError {
+    fn from(error: reqwest::Error) -> Self {
+        return DownloadError::Http(error.into());
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<io::Error> for DownloadError {
+    fn from(error: io::Error) -> Self {
+        return DownloadError::Io(error);
+    }
+}
+
+impl SellAppClient {
+    /// Get the deliverables of an invoice, see ``Deliverable::parse_all``.
+    ///
+    /// ``invoice_id``: The ID of the invoice, required.
+    pub async fn invoices_get_deliverables(
+        &self,
+        invoice_id: String,
+    ) -> Result<Vec<Deliverable>, Error> {
+        let res = self
+            .invoices_get_items(invoice_id)
+            .await?
+            .error_for_status()?;
+        return Ok(Deliverable::parse_all(&res.json::<Value>().await?));
+    }
+
+    /// Download a file deliverable into ``dir``, streaming it to disk.
+    ///
+    /// The file is written to ``<name>.part`` first and renamed once complete and verified. If a ``.part`` file is
+    /// left over from an interrupted download, only the rest of the file is requested, and nothing at all if it
+    /// already has the advertised size. If the server answers with another range than requested, the whole file
+    /// is downloaded again. Files are named after ``FileDeliverable::name``, or the last segment of the URL.
+    ///
+    /// ```no_run
+    /// use sellapp::deliverable::Deliverable;
+    ///
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let sellapp_api = sellapp::init("your_api_key", "");
+    /// for deliverable in sellapp_api.invoices_get_deliverables("123".to_string()).await? {
+    ///     if let Deliverable::File(file) = deliverable {
+    ///         let download = sellapp_api.download_deliverable(&file, "downloads").await?;
+    ///         println!("Saved {:?}", download.path);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn download_deliverable(
+        &self,
+        file: &FileDeliverable,
+        dir: impl AsRef<Path>,
+    ) -> Result<Download, DownloadError> {
+        fs::create_dir_all(&dir)?;
+        let path = dir.as_ref().join(file_name(file));
+        let part_path = path.with_file_name(format!(
+            "{}.part",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        ));
+
+        let mut hasher = Sha256::new();
+        let mut resumed_from = 0;
+        if let Ok(mut part) = fs::File::open(&part_path) {
+            let mut buffer = vec![0; 64 * 1024];
+            loop {
+                let read = part.read(&mut buffer)?;
+                if read == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..read]);
+                resumed_from += read as u64;
+            }
+        }
+        if file.size.is_some_and(|size| resumed_from > size) {
+            hasher = Sha256::new();
+            resumed_from = 0;
+        }
+
+        // A complete ``.part`` file only needs to be verified, and the server would answer 416 anyway.
+        let complete = resumed_from > 0 && file.size == Some(resumed_from);
+        let mut size = resumed_from;
+        if !complete {
+            let mut res = self.download_request(file, resumed_from).await?;
+            if range_mismatch(&res, resumed_from) {
+                res = self.download_request(file, 0).await?;
+                resumed_from = 0;
+            }
+
+            // On 416 the ``.part`` file already holds the whole file.
+            if res.status() != StatusCode::RANGE_NOT_SATISFIABLE {
+                let mut output = OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .truncate(false)
+                    .open(&part_path)?;
+                if res.status() == StatusCode::PARTIAL_CONTENT && resumed_from > 0 {
+                    output.set_len(resumed_from)?;
+                    output.sync_all()?;
+                    output = OpenOptions::new().append(true).open(&part_path)?;
+                } else {
+                    output.set_len(0)?;
+                    hasher = Sha256::new();
+                    resumed_from = 0;
+                    size = 0;
+                }
+
+                while let Some(chunk) = res.chunk().await? {
+                    output.write_all(&chunk)?;
+                    hasher.update(&chunk);
+                    size += chunk.len() as u64;
+                }
+                output.flush()?;
+            }
+        }
+
+        let verified = match verify(file, size, hasher) {
+            Ok(verified) => verified,
+            Err(error) => {
+                let _ = fs::remove_file(&part_path);
+                return Err(error);
+            }
+        };
+        fs::rename(&part_path, &path)?;
+        return Ok(Download {
+            path: Some(path),
+            size,
+            resumed_from,
+            verified,
+        });
+    }
+
+    /// Download a file deliverable into ``writer``, streaming it chunk by chunk.
+    ///
+    /// ``resume_from``: Amount of bytes of the file the writer already has, to only request the rest. The checksum
+    /// can only be verified for downloads starting at 0. If the server doesn't support resuming, the whole file
+    /// is written again and ``Download::resumed_from`` is 0. If the writer already has the whole file, nothing
+    /// is written. Fails with ``DownloadError::RangeMismatch`` if the server answers with another range than requested.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn download_deliverable_to<W: AsyncWrite + Unpin>(
+        &self,
+        file: &FileDeliverable,
+        mut writer: W,
+        resume_from: u64,
+    ) -> Result<Download, DownloadError> {
+        let complete = Download {
+            path: None,
+            size: resume_from,
+            resumed_from: resume_from,
+            verified: false,
+        };
+        if resume_from > 0 && file.size == Some(resume_from) {
+            return Ok(complete);
+        }
+        let mut res = self.download_request(file, resume_from).await?;
+        if range_mismatch(&res, resume_from) {
+            return Err(DownloadError::RangeMismatch {
+                requested: resume_from,
+                content_range: content_range(&res).map(str::to_string),
+            });
+        }
+        let resumed_from = match res.status() {
+            StatusCode::RANGE_NOT_SATISFIABLE => {
+                check_size(file, resume_from)?;
+                return Ok(complete);
+            }
+            StatusCode::PARTIAL_CONTENT => resume_from,
+            _ => 0,
+        };
+
+        let mut hasher = Sha256::new();
+        let mut size = resumed_from;
+        while let Some(chunk) = res.chunk().await? {
+            writer.write_all(&chunk).await?;
+            hasher.update(&chunk);
+            size += chunk.len() as u64;
+        }
+        writer.flush().await?;
+
+        let verified = match resumed_from {
+            0 => verify(file, size, hasher)?,
+            _ => false,
+        };
+        return Ok(Download {
+            path: None,
+            size,
+            resumed_from,
+            verified,
+        });
+    }
+
+    /// Request a file, from ``offset`` on. The API key is only sent to Sell.App itself, not to file hosts.
+    ///
+    /// **416 Range Not Satisfiable** is returned as a response for ``offset`` > 0, as the file is complete then.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn download_request(
+        &self,
+        file: &FileDeliverable,
+        offset: u64,
+    ) -> Result<Response, DownloadError> {
+        let url = match Url::parse(&file.url) {
+            Ok(url) => url,
+            Err(_) => Url::parse("https://sell.app/api/")
+                .unwrap()
+                .join(file.url.trim_start_matches('/'))
+                .map_err(|_| DownloadError::InvalidUrl(file.url.clone()))?,
+        };
+        let mut req = self.http_client.request(Method::GET, url.clone());
+        if url.host_str() == Some("sell.app") {
+            req = req.headers(self.generate_headers(vec![]));
+        }
+        if offset > 0 {
+            req = req.header(RANGE, format!("bytes={}-", offset));
+        }
+        let res = self
+            .transport
+            .execute(req.build()?)
+            .await
+            .map_err(crate::Error::from)?;
+        if offset > 0 && res.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+            return Ok(res);
+        }
+        return Ok(res.error_for_status()?);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn content_range(res: &Response) -> Option<&str> {
+    return res.headers().get(CONTENT_RANGE)?.to_str().ok();
+}
+
+/// Whether a response to a request from ``offset`` on is a **206** whose ``Content-Range`` doesn't start at
+/// ``offset``, so it can't be appended to the bytes already there.
+#[cfg(not(target_arch = "wasm32"))]
+fn range_mismatch(res: &Response, offset: u64) -> bool {
+    if offset == 0 || res.status() != StatusCode::PARTIAL_CONTENT {
+        return false;
+    }
+    let start = content_range(res)
+        .and_then(|range| range.trim().strip_prefix("bytes "))
+        .and_then(|range| range.split_once('-'))
+        .and_then(|(start, _)| start.trim().parse::<u64>().ok());
+    return start != Some(offset);
+}
+
+/// Get a safe file name for a download, without any directories.
+#[cfg(not(target_arch = "wasm32"))]
+fn file_name(file: &FileDeliverable) -> String {
+    let from_url = Url::parse(&file.url).ok().and_then(|url| {
+        url.path_segments()?
+            .next_back()
+            .filter(|segment| !segment.is_empty())
+            .map(str::to_string)
+    });
+    let name = file.name.clone().or(from_url).unwrap_or_default();
+    return match Path::new(&name).file_name() {
+        Some(name) => name.to_string_lossy().into_owned(),
+        None => "deliverable".to_string(),
+    };
+}
+
+/// Check the size of a finished download, if the size of the file is known.
+#[cfg(not(target_arch = "wasm32"))]
+fn check_size(file: &FileDeliverable, size: u64) -> Result<(), DownloadError> {
+    if let Some(expected) = file.size {
+        if expected != size {
+            return Err(DownloadError::SizeMismatch {
+                expected,
+                actual: size,
+            });
+        }
+    }
+    return Ok(());
+}
+
+/// Check the size and checksum of a finished download. Returns whether the checksum was checked.
+#[cfg(not(target_arch = "wasm32"))]
+fn verify(file: &FileDeliverable, size: u64, hasher: Sha256) -> Result<bool, DownloadError> {
+    check_size(file, size)?;
+    let expected = match &file.sha256 {
+        Some(expected) => expected,
+        None => return Ok(false),
+    };
+    let actual: String = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    if actual != *expected {
+        return Err(DownloadError::ChecksumMismatch {
+            expected: expected.clone(),
+            actual,
+        });
+    }
+    return Ok(true);
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::transport::mock::{self, MockTransport};
+
+    fn file(url: &str) -> FileDeliverable {
+        return FileDeliverable {
+            url: url.to_string(),
+            ..FileDeliverable::default()
+        };
+    }
+
+    fn response(status: u16, content_range: Option<&str>) -> Response {
+        let mut builder = http::Response::builder().status(status);
+        if let Some(content_range) = content_range {
+            builder = builder.header(CONTENT_RANGE, content_range);
+        }
+        return Response::from(builder.body("").unwrap());
+    }
+
+    fn sha256(bytes: &[u8]) -> Sha256 {
+        let mut hasher = Sha256::new();
+        hasher.update(bytes);
+        return hasher;
+    }
+
+    #[test]
+    fn parses_every_kind_of_deliverable() {
+        let body = json!({ "data": [
+            { "data": { "serials": "KEY-1\n\n KEY-2 \n", "text": "Enjoy!" } },
+            { "serials": ["KEY-3", 4] },
+            { "data": { "files": [
+                { "download_url": "https://files.example.com/a.zip", "filename": "a.zip", "size": "12", "checksum": "sha256:ABC" },
+                { "name": "no-url.zip" },
+            ] } },
+            { "dynamic": { "code": "XYZ" } },
+            { "unknown": true },
+        ] });
+        assert_eq!(
+            Deliverable::parse_all(&body),
+            [
+                Deliverable::Serials(vec!["KEY-1".to_string(), "KEY-2".to_string()]),
+                Deliverable::Text("Enjoy!".to_string()),
+                Deliverable::Serials(vec!["KEY-3".to_string()]),
+                Deliverable::File(FileDeliverable {
+                    url: "https://files.example.com/a.zip".to_string(),
+                    name: Some("a.zip".to_string()),
+                    size: Some(12),
+                    sha256: Some("abc".to_string()),
+                }),
+                Deliverable::Other(json!({ "name": "no-url.zip" })),
+                Deliverable::Dynamic(json!({ "code": "XYZ" })),
+                Deliverable::Other(json!({ "unknown": true })),
+            ]
+        );
+
+        let single = json!({ "data": { "file": { "url": "/files/1" } } });
+        assert_eq!(
+            Deliverable::parse_all(&single),
+            [Deliverable::File(file("/files/1"))]
+        );
+        assert_eq!(Deliverable::parse_all(&json!({ "data": [] })), []);
+    }
+
+    #[test]
+    fn detects_range_mismatches() {
+        assert!(!range_mismatch(
+            &response(206, Some("bytes 100-199/200")),
+            100
+        ));
+        assert!(range_mismatch(&response(206, Some("bytes 0-199/200")), 100));
+        assert!(range_mismatch(
+            &response(206, Some("items 100-199/200")),
+            100
+        ));
+        assert!(range_mismatch(&response(206, None), 100));
+        // A full response replaces the file instead, and nothing was requested from an offset at 0.
+        assert!(!range_mismatch(&response(200, None), 100));
+        assert!(!range_mismatch(&response(206, Some("bytes 5-9/10")), 0));
+    }
+
+    #[test]
+    fn names_files_safely() {
+        let mut named = file("https://files.example.com/download/game.zip?token=1");
+        assert_eq!(file_name(&named), "game.zip");
+        named.name = Some("../../etc/passwd".to_string());
+        assert_eq!(file_name(&named), "passwd");
+        assert_eq!(
+            file_name(&file("https://files.example.com/")),
+            "deliverable"
+        );
+        assert_eq!(file_name(&file("/files/1")), "deliverable");
+    }
+
+    #[test]
+    fn verifies_size_and_checksum() {
+        let mut checked = file("https://files.example.com/a.txt");
+        assert!(!verify(&checked, 5, sha256(b"hello")).unwrap());
+
+        checked.size = Some(5);
+        checked.sha256 = Some(format!("{:x}", sha256(b"hello").finalize()));
+        assert!(verify(&checked, 5, sha256(b"hello")).unwrap());
+        assert!(matches!(
+            verify(&checked, 4, sha256(b"hell")),
+            Err(DownloadError::SizeMismatch {
+                expected: 5,
+                actual: 4
+            })
+        ));
+        assert!(matches!(
+            verify(&checked, 5, sha256(b"world")),
+            Err(DownloadError::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn treats_unsatisfiable_resumes_as_complete() {
+        let transport = MockTransport::new(|_, _| mock::json(416, json!({})));
+        let mut resumed = file("https://files.example.com/a.txt");
+        resumed.sha256 = Some(format!("{:x}", sha256(b"hello").finalize()));
+        let mut written = Vec::new();
+        let download = mock::client(&transport)
+            .download_deliverable_to(&resumed, &mut written, 5)
+            .await
+            .unwrap();
+        assert_eq!(
+            (download.size, download.resumed_from, download.verified),
+            (5, 5, false)
+        );
+        assert!(written.is_empty());
+
+        resumed.size = Some(6);
+        let error = mock::client(&transport)
+            .download_deliverable_to(&resumed, &mut written, 5)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, DownloadError::SizeMismatch { .. }));
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_relative_urls() {
+        let transport = MockTransport::new(|_, _| mock::json(200, json!({})));
+        let error = mock::client(&transport)
+            .download_deliverable_to(&file("https://[::1/a.zip"), Vec::new(), 0)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, DownloadError::InvalidUrl(_)), "{}", error);
+        assert!(transport.requests().is_empty());
+    }
+}
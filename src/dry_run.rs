@@ -0,0 +1,137 @@
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use reqwest::{
+    header::{HeaderValue, CONTENT_TYPE},
+    Method, Request, Response, ResponseBuilderExt, StatusCode,
+};
+use serde_json::{json, Value};
+
+use crate::middleware::{LocalResponse, Middleware, RequestContext};
+
+/// A mutating call that was not sent because of dry-run mode.
+#[derive(Debug, Clone)]
+pub struct DryRunEntry {
+    /// Name of the client method that issued the call, e.g. **"products_create"**.
+    pub operation: &'static str,
+    pub method: Method,
+    /// Path of the request, including any URL parameters, e.g. **"v2/products/12"**.
+    pub path: String,
+    /// Body of the request, if any.
+    pub body: Option<String>,
+}
+
+impl fmt::Display for DryRunEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} ({})", self.method, self.path, self.operation)?;
+        if let Some(body) = &self.body {
+            write!(f, " {}", body)?;
+        }
+        return Ok(());
+    }
+}
+
+/// Middleware that blocks every mutating call and records it instead, see ``SellAppClient::with_dry_run``.
+///
+/// GET calls are sent as usual. Clones share the same log, so keep a clone to inspect the calls afterwards.
+#[derive(Clone, Default)]
+pub struct DryRun {
+    entries: Arc<Mutex<Vec<DryRunEntry>>>,
+}
+
+impl DryRun {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Get the calls that were blocked so far, oldest first.
+    pub fn entries(&self) -> Vec<DryRunEntry> {
+        return self.entries.lock().unwrap().clone();
+    }
+
+    /// Get and clear the calls that were blocked so far.
+    pub fn take(&self) -> Vec<DryRunEntry> {
+        return std::mem::take(&mut *self.entries.lock().unwrap());
+    }
+}
+
+impl Middleware for DryRun {
+    fn before_request(&self, ctx: &RequestContext, request: &mut Request) -> Option<Response> {
+        if ctx.method == Method::GET {
+            return None;
+        }
+
+        let url = request.url();
+        let mut path = url.path().trim_start_matches("/api/").to_string();
+        if let Some(query) = url.query() {
+            path = format!("{}?{}", path, query);
+        }
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map(|bytes| String::from_utf8_lossy(bytes).into_owned());
+
+        let mut entries = self.entries.lock().unwrap();
+        entries.push(DryRunEntry {
+            operation: ctx.operation,
+            method: ctx.method.clone(),
+            path,
+            body: body.clone(),
+        });
+        let count = entries.len();
+        drop(entries);
+
+        return Some(synthetic_response(ctx, body.as_deref(), count));
+    }
+}
+
+/// Build the response a blocked call returns: the request body wrapped in **"data"**, with a placeholder ID for
+/// created items, or an empty 204 response for deletes.
+fn synthetic_response(ctx: &RequestContext, body: Option<&str>, count: usize) -> Response {
+    let builder = http::Response::builder()
+        .url(ctx.url.clone())
+        .extension(LocalResponse::DryRun);
+    if ctx.method == Method::DELETE {
+        let res = builder
+            .status(StatusCode::NO_CONTENT)
+            .body(Vec::new())
+            .unwrap();
+        return Response::from(res);
+    }
+
+    let mut data = body
+        .and_then(|body| serde_json::from_str::<Value>(body).ok())
+        .filter(Value::is_object)
+        .unwrap_or_else(|| json!({}));
+    let status = if creates_item(ctx) {
+        data["id"] = json!(format!("dry-run-{}", count));
+        StatusCode::CREATED
+    } else {
+        StatusCode::OK
+    };
+
+    let res = builder
+        .status(status)
+        .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+        .body(json!({ "data": data }).to_string().into_bytes())
+        .unwrap();
+    return Response::from(res);
+}
+
+/// Whether a call creates an item: a POST to a collection, like **"v2/products"**, **"v2/products/12/variants"**
+/// or **"v1/tickets/3/messages"**. POSTs to actions like **"v2/invoices/5/checkout"** don't.
+fn creates_item(ctx: &RequestContext) -> bool {
+    if ctx.method != Method::POST {
+        return false;
+    }
+    let path = ctx
+        .url
+        .path()
+        .trim_start_matches("/api/")
+        .trim_end_matches('/');
+    // Skip the version prefix, e.g. "v2".
+    let segments: Vec<&str> = path.split('/').skip(1).collect();
+    return matches!(segments.as_slice(), [_] | [_, _, "variants" | "messages"]);
+}
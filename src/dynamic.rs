@@ -0,0 +1,418 @@
+use std::{error::Error, future::Future, io, sync::Arc, time::Duration};
+
+use futures::future::BoxFuture;
+use hmac::{Hmac, Mac};
+use http::{header::CONTENT_TYPE, HeaderMap, Method, Response, StatusCode};
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::{body::Incoming, server::conn::http1, service::service_fn};
+use hyper_util::rt::TokioIo;
+use serde::de::Error as _;
+use serde_json::Value;
+use sha2::Sha256;
+use tokio::net::TcpListener;
+
+use crate::{
+    models::{Invoice, InvoiceProduct, InvoiceVariant},
+    util::unwrap_data,
+};
+
+/// Header Sell.App puts the hex HMAC-SHA256 signature of the request body in.
+pub const SIGNATURE_HEADER: &str = "signature";
+
+/// How long the generator may take by default before the request is answered with **504 Gateway Timeout**.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Requests with a larger body are rejected with **413 Payload Too Large** without being read.
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// A dynamic delivery request sent by Sell.App when a dynamic product is bought.
+#[derive(Debug, Clone)]
+pub struct DynamicRequest {
+    /// The invoice that was paid.
+    pub invoice: Invoice,
+    /// The product that was bought, if included in the request.
+    pub product: Option<InvoiceProduct>,
+    /// The variant to generate deliverables for.
+    pub variant: InvoiceVariant,
+    /// How many units were bought, and so how many deliverables to generate.
+    pub quantity: u32,
+    /// The request body as sent, for attributes that aren't typed.
+    pub payload: Value,
+}
+
+impl DynamicRequest {
+    /// Read a dynamic delivery request body.
+    ///
+    /// The variant is taken from ``variant`` (or ``product_variant``), or from the invoice if it only contains a
+    /// single variant. ``quantity`` defaults to the quantity of the variant.
+    pub fn parse(body: &[u8]) -> Result<Self, serde_json::Error> {
+        let payload = unwrap_data(serde_json::from_slice(body)?);
+        let invoice: Invoice = match &payload["invoice"] {
+            Value::Object(_) => serde_json::from_value(payload["invoice"].clone())?,
+            _ => Invoice::default(),
+        };
+        let product: Option<InvoiceProduct> = match &payload["product"] {
+            Value::Object(_) => Some(serde_json::from_value(payload["product"].clone())?),
+            _ => None,
+        };
+        let variant: InvoiceVariant = match (&payload["variant"], &payload["product_variant"]) {
+            (Value::Object(_), _) => serde_json::from_value(payload["variant"].clone())?,
+            (_, Value::Object(_)) => serde_json::from_value(payload["product_variant"].clone())?,
+            _ => match &invoice.products[..] {
+                [product] if product.variants.len() == 1 => product.variants[0].clone(),
+                _ => return Err(serde_json::Error::custom("missing the variant to deliver")),
+            },
+        };
+        let quantity = match payload["quantity"].as_u64() {
+            Some(quantity) => quantity as u32,
+            None => variant.quantity.max(1),
+        };
+        return Ok(DynamicRequest {
+            invoice,
+            product,
+            variant,
+            quantity,
+            payload,
+        });
+    }
+}
+
+/// Future returned by ``DeliveryGenerator::generate``.
+pub type GeneratorFuture = BoxFuture<'static, Result<Vec<String>, Box<dyn Error + Send + Sync>>>;
+
+/// Generates the deliverables of a dynamic product, e.g. by creating license keys or accounts.
+///
+/// Implemented for any ``Fn(DynamicRequest)`` returning a future, so a closure or an ``async fn`` can be used.
+/// Return one deliverable per bought unit; they are sent to Sell.App one per line.
+pub trait DeliveryGenerator: Send + Sync {
+    fn generate(&self, request: DynamicRequest) -> GeneratorFuture;
+}
+
+impl<F, Fut, E> DeliveryGenerator for F
+where
+    F: Fn(DynamicRequest) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Vec<String>, E>> + Send + 'static,
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    fn generate(&self, request: DynamicRequest) -> GeneratorFuture {
+        let future = self(request);
+        return Box::pin(async move { future.await.map_err(Into::into) });
+    }
+}
+
+/// Answers the dynamic delivery requests of Sell.App with deliverables from a ``DeliveryGenerator``.
+///
+/// Requests are only passed to the generator if their signature matches ``secret``, the webhook secret of your
+/// store. Use ``handle`` to plug it into an existing web server, or ``serve`` to run it on its own.
+///
+/// Responses are plain text: the deliverables on **200 OK**, or a short reason on **400** (bad payload),
+/// **401** (bad signature), **405** (not a POST), **413** (body too large), **500** (generator failed) or
+/// **504** (generator timed out). Generator errors aren't included in responses, as they're shown to customers.
+///
+/// **Note**: The signature only covers the body, without a timestamp or nonce, so a captured request stays valid
+/// and can be sent again. There is no replay protection: make the generator idempotent where duplicates matter,
+/// e.g. by returning the deliverables already generated for the invoice and variant.
+///
+/// ```no_run
+/// use sellapp::dynamic::{DynamicDelivery, DynamicRequest};
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let delivery = DynamicDelivery::new("your_webhook_secret", |request: DynamicRequest| async move {
+///     let keys = (0..request.quantity).map(|i| format!("KEY-{}-{}", request.invoice.id, i));
+///     Ok::<_, std::io::Error>(keys.collect())
+/// });
+/// let listener = tokio::net::TcpListener::bind("0.0.0.0:8080").await?;
+/// delivery.serve(listener).await
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct DynamicDelivery {
+    secret: Vec<u8>,
+    generator: Arc<dyn DeliveryGenerator>,
+    timeout: Duration,
+}
+
+impl DynamicDelivery {
+    pub fn new(secret: impl AsRef<[u8]>, generator: impl DeliveryGenerator + 'static) -> Self {
+        return DynamicDelivery {
+            secret: secret.as_ref().to_vec(),
+            generator: Arc::new(generator),
+            timeout: DEFAULT_TIMEOUT,
+        };
+    }
+
+    /// Set how long the generator may take, ``DEFAULT_TIMEOUT`` by default.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        return self;
+    }
+
+    /// Check the signature header of a request against its body, in constant time. This doesn't detect replayed
+    /// requests, see ``DynamicDelivery``.
+    pub fn verify(&self, headers: &HeaderMap, body: &[u8]) -> bool {
+        let signature = match headers
+            .get(SIGNATURE_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(decode_hex)
+        {
+            Some(signature) => signature,
+            None => return false,
+        };
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC accepts any key length");
+        mac.update(body);
+        return mac.verify_slice(&signature).is_ok();
+    }
+
+    /// Answer a single dynamic delivery request.
+    pub async fn handle(
+        &self,
+        method: &Method,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Response<String> {
+        if method != Method::POST {
+            return text_response(StatusCode::METHOD_NOT_ALLOWED, "Only POST is allowed.");
+        }
+        if !self.verify(headers, body) {
+            return text_response(StatusCode::UNAUTHORIZED, "Invalid signature.");
+        }
+        let request = match DynamicRequest::parse(body) {
+            Ok(request) => request,
+            Err(error) => {
+                return text_response(
+                    StatusCode::BAD_REQUEST,
+                    &format!("Invalid payload: {}", error),
+                )
+            }
+        };
+        return match tokio::time::timeout(self.timeout, self.generator.generate(request)).await {
+            Ok(Ok(deliverables)) => text_response(StatusCode::OK, &deliverables.join("\n")),
+            Ok(Err(_)) => text_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to generate the deliverable.",
+            ),
+            Err(_) => text_response(
+                StatusCode::GATEWAY_TIMEOUT,
+                "Timed out generating the deliverable.",
+            ),
+        };
+    }
+
+    /// Answer dynamic delivery requests on ``listener`` over HTTP/1, on any path.
+    ///
+    /// Each connection is handled in its own task. Returns only if accepting a connection fails.
+    pub async fn serve(self, listener: TcpListener) -> io::Result<()> {
+        let delivery = Arc::new(self);
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let delivery = delivery.clone();
+            tokio::spawn(async move {
+                let service = service_fn(move |request| {
+                    let delivery = delivery.clone();
+                    async move { Ok::<_, hyper::Error>(delivery.handle_hyper(request).await) }
+                });
+                // Connection errors only affect the client of that connection.
+                let _ = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await;
+            });
+        }
+    }
+
+    async fn handle_hyper(&self, request: http::Request<Incoming>) -> Response<Full<bytes::Bytes>> {
+        let (parts, body) = request.into_parts();
+        let response = match Limited::new(body, MAX_BODY_SIZE).collect().await {
+            Ok(body) => {
+                self.handle(&parts.method, &parts.headers, &body.to_bytes())
+                    .await
+            }
+            Err(_) => text_response(StatusCode::PAYLOAD_TOO_LARGE, "Request body is too large."),
+        };
+        return response.map(|body| Full::new(body.into()));
+    }
+}
+
+/// Sign a request body like Sell.App does, e.g. to test a ``DynamicDelivery`` locally.
+pub fn sign(secret: impl AsRef<[u8]>, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_ref()).expect("HMAC accepts any key length");
+    mac.update(body);
+    return mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+}
+
+fn text_response(status: StatusCode, text: &str) -> Response<String> {
+    let mut response = Response::new(text.to_string());
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, "text/plain; charset=utf-8".parse().unwrap());
+    return response;
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    let hex = hex.trim();
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    return (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+    use serde_json::json;
+
+    use super::*;
+
+    const SECRET: &str = "webhook-secret";
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        return runtime.block_on(future);
+    }
+
+    fn signed(body: &[u8]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let signature = sign(SECRET, body);
+        headers.insert(SIGNATURE_HEADER, HeaderValue::from_str(&signature).unwrap());
+        return headers;
+    }
+
+    fn body() -> Vec<u8> {
+        let body = json!({
+            "invoice": { "id": 42 },
+            "variant": { "id": 7, "quantity": 2 },
+        });
+        return body.to_string().into_bytes();
+    }
+
+    fn delivery() -> DynamicDelivery {
+        return DynamicDelivery::new(SECRET, |request: DynamicRequest| async move {
+            let keys = (0..request.quantity).map(|i| format!("{}-{}", request.invoice.id, i));
+            Ok::<_, io::Error>(keys.collect())
+        });
+    }
+
+    fn handle(delivery: &DynamicDelivery, headers: &HeaderMap, body: &[u8]) -> Response<String> {
+        return block_on(delivery.handle(&Method::POST, headers, body));
+    }
+
+    #[test]
+    fn signs_with_hmac_sha256() {
+        // RFC 4231, test case 2.
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn verifies_signatures() {
+        let delivery = delivery();
+        let body = body();
+        assert!(delivery.verify(&signed(&body), &body));
+
+        let mut headers = HeaderMap::new();
+        let uppercase = sign(SECRET, &body).to_uppercase();
+        headers.insert(SIGNATURE_HEADER, HeaderValue::from_str(&uppercase).unwrap());
+        assert!(delivery.verify(&headers, &body));
+
+        assert!(!delivery.verify(&signed(b"other body"), &body));
+        assert!(!delivery.verify(&HeaderMap::new(), &body));
+        let other_secret = DynamicDelivery::new("other-secret", |_: DynamicRequest| async {
+            Ok::<_, io::Error>(Vec::new())
+        });
+        assert!(!other_secret.verify(&signed(&body), &body));
+
+        for signature in ["", "abc", "zz", &sign(SECRET, &body)[2..]] {
+            let mut headers = HeaderMap::new();
+            headers.insert(SIGNATURE_HEADER, HeaderValue::from_str(signature).unwrap());
+            assert!(!delivery.verify(&headers, &body), "{:?}", signature);
+        }
+    }
+
+    #[test]
+    fn answers_with_deliverables() {
+        let body = body();
+        let response = handle(&delivery(), &signed(&body), &body);
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), "42-0\n42-1");
+    }
+
+    #[test]
+    fn rejects_other_methods() {
+        let body = body();
+        let response = block_on(delivery().handle(&Method::GET, &signed(&body), &body));
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[test]
+    fn rejects_invalid_signatures() {
+        let body = body();
+        let response = handle(&delivery(), &signed(b"{}"), &body);
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = handle(&delivery(), &HeaderMap::new(), &body);
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn rejects_invalid_payloads() {
+        for body in [&b"not json"[..], br#"{"invoice": {"id": 42}}"#] {
+            let response = handle(&delivery(), &signed(body), body);
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[test]
+    fn hides_generator_errors() {
+        let delivery = DynamicDelivery::new(SECRET, |_: DynamicRequest| async {
+            Err::<Vec<String>, _>(io::Error::other("database password is hunter2"))
+        });
+        let body = body();
+        let response = handle(&delivery, &signed(&body), &body);
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(!response.body().contains("hunter2"));
+    }
+
+    #[test]
+    fn times_out_slow_generators() {
+        let delivery = DynamicDelivery::new(SECRET, |_: DynamicRequest| async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok::<_, io::Error>(Vec::new())
+        })
+        .with_timeout(Duration::from_millis(10));
+        let body = body();
+        let response = handle(&delivery, &signed(&body), &body);
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+    }
+
+    #[test]
+    fn parses_requests() {
+        let request = DynamicRequest::parse(&body()).unwrap();
+        assert_eq!(request.invoice.id, "42");
+        assert_eq!(request.variant.id, "7");
+        assert_eq!(request.quantity, 2);
+
+        let single_variant = json!({
+            "data": {
+                "invoice": { "id": 1, "products": [{ "id": 3, "variants": [{ "id": 9, "quantity": 1 }] }] },
+                "quantity": 4,
+            }
+        });
+        let request = DynamicRequest::parse(single_variant.to_string().as_bytes()).unwrap();
+        assert_eq!(request.variant.id, "9");
+        assert_eq!(request.quantity, 4);
+    }
+}
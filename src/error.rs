@@ -0,0 +1,74 @@
+use std::{error, fmt};
+
+use reqwest::StatusCode;
+
+use crate::transport::{TransportError, TransportErrorKind};
+
+/// Error returned by the methods of ``SellAppClient``.
+#[derive(Debug)]
+pub enum Error {
+    /// The transport failed to send the request or to receive a response, see ``transport::TransportError``.
+    Transport(TransportError),
+    /// Building the request or reading the response failed, or the API responded with an error status code
+    /// passed through ``Response::error_for_status``.
+    Http(reqwest::Error),
+}
+
+impl Error {
+    /// Whether the request or reading its response timed out.
+    pub fn is_timeout(&self) -> bool {
+        return match self {
+            Error::Transport(error) => error.kind() == TransportErrorKind::Timeout,
+            Error::Http(error) => error.is_timeout(),
+        };
+    }
+
+    /// Whether connecting to the API failed, so the request was never sent.
+    pub fn is_connect(&self) -> bool {
+        return match self {
+            Error::Transport(error) => error.kind() == TransportErrorKind::Connect,
+            #[cfg(not(target_arch = "wasm32"))]
+            Error::Http(error) => error.is_connect(),
+            #[cfg(target_arch = "wasm32")]
+            Error::Http(_) => false,
+        };
+    }
+
+    /// The status code of the response, for errors created by ``Response::error_for_status``.
+    pub fn status(&self) -> Option<StatusCode> {
+        return match self {
+            Error::Transport(_) => None,
+            Error::Http(error) => error.status(),
+        };
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            Error::Transport(error) => write!(f, "{}", error),
+            Error::Http(error) => write!(f, "{}", error),
+        };
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        return match self {
+            Error::Transport(error) => Some(error),
+            Error::Http(error) => Some(error),
+        };
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(error: reqwest::Error) -> Self {
+        return Error::Http(error);
+    }
+}
+
+impl From<TransportError> for Error {
+    fn from(error: TransportError) -> Self {
+        return Error::Transport(error);
+    }
+}
@@ -0,0 +1,387 @@
+use std::{fmt, io};
+
+use crate::{
+    models::Invoice,
+    resource::{ListQuery, Resource, PAGE_SIZE},
+    timestamp::Timestamp,
+    SellAppClient,
+};
+
+/// A column of the invoice export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Id,
+    Status,
+    Gateway,
+    Currency,
+    Subtotal,
+    Total,
+    /// ID of the coupon applied to the invoice. The API doesn't include the coupon code in invoices, look it up
+    /// with ``SellAppClient::coupons_get`` if needed.
+    CouponId,
+    CustomerEmail,
+    Country,
+    /// Every bought variant as **"product_id:variant_id x quantity"**, separated by **"; "**.
+    LineItems,
+    CreatedAt,
+    UpdatedAt,
+}
+
+impl Column {
+    pub const ALL: [Column; 12] = [
+        Column::Id,
+        Column::Status,
+        Column::Gateway,
+        Column::Currency,
+        Column::Subtotal,
+        Column::Total,
+        Column::CouponId,
+        Column::CustomerEmail,
+        Column::Country,
+        Column::LineItems,
+        Column::CreatedAt,
+        Column::UpdatedAt,
+    ];
+
+    /// Name of the column in the header of the export.
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Column::Id => "id",
+            Column::Status => "status",
+            Column::Gateway => "gateway",
+            Column::Currency => "currency",
+            Column::Subtotal => "subtotal",
+            Column::Total => "total",
+            Column::CouponId => "coupon_id",
+            Column::CustomerEmail => "customer_email",
+            Column::Country => "country",
+            Column::LineItems => "line_items",
+            Column::CreatedAt => "created_at",
+            Column::UpdatedAt => "updated_at",
+        };
+    }
+
+    /// Get the value of this column for ``invoice``.
+    pub fn value(&self, invoice: &Invoice) -> Option<String> {
+        return match self {
+            Column::Id => Some(invoice.id.clone()),
+            Column::Status => invoice.status.clone(),
+            Column::Gateway => invoice.payment.gateway.kind.clone(),
+            Column::Currency => invoice
+                .payment
+                .total
+                .map(|total| total.currency.to_string()),
+            Column::Subtotal => invoice.payment.subtotal.map(|a| a.to_major_string()),
+            Column::Total => invoice.payment.total.map(|a| a.to_major_string()),
+            Column::CouponId => invoice.coupon_id.clone(),
+            Column::CustomerEmail => invoice.customer_information.email.clone(),
+            Column::Country => invoice.customer_information.country.clone(),
+            Column::LineItems => {
+                let items: Vec<String> = invoice
+                    .products
+                    .iter()
+                    .flat_map(|product| {
+                        product.variants.iter().map(move |variant| {
+                            format!("{}:{} x {}", product.id, variant.id, variant.quantity)
+                        })
+                    })
+                    .collect();
+                Some(items.join("; "))
+            }
+            Column::CreatedAt => invoice.created_at.map(|t| t.to_string()),
+            Column::UpdatedAt => invoice.updated_at.map(|t| t.to_string()),
+        };
+    }
+}
+
+/// Which invoices and columns to export.
+#[derive(Debug, Clone)]
+pub struct ExportConfig {
+    pub columns: Vec<Column>,
+    /// Only export invoices created at or after this timestamp.
+    pub created_from: Option<Timestamp>,
+    /// Only export invoices created before this timestamp.
+    pub created_to: Option<Timestamp>,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        return ExportConfig {
+            columns: Column::ALL.to_vec(),
+            created_from: None,
+            created_to: None,
+        };
+    }
+}
+
+impl ExportConfig {
+    /// The date range as invoice list parameters, so the API only returns matching invoices.
+    fn to_query(&self) -> ListQuery {
+        let mut query = ListQuery::new();
+        if let Some(from) = self.created_from {
+            query = query.created_from(from);
+        }
+        if let Some(to) = self.created_to {
+            query = query.created_to(to);
+        }
+        return query;
+    }
+
+    /// Whether ``invoice`` is in the date range. The API filter includes ``created_to`` itself, so it's checked again.
+    fn includes(&self, invoice: &Invoice) -> bool {
+        if let Some(from) = self.created_from {
+            if invoice
+                .created_at
+                .is_none_or(|created_at| created_at < from)
+            {
+                return false;
+            }
+        }
+        if let Some(to) = self.created_to {
+            if invoice.created_at.is_none_or(|created_at| created_at >= to) {
+                return false;
+            }
+        }
+        return true;
+    }
+}
+
+/// Error returned while exporting invoices.
+#[derive(Debug)]
+pub enum ExportError {
+    /// Fetching invoices from the API failed.
+    Http(crate::Error),
+    /// Parsing an invoice failed.
+    Json(serde_json::Error),
+    /// Writing the export failed.
+    Write(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            ExportError::Http(error) => write!(f, "failed to fetch invoices: {}", error),
+            ExportError::Json(error) => write!(f, "failed to parse invoice: {}", error),
+            ExportError::Write(error) => write!(f, "failed to write export: {}", error),
+        };
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+impl From<crate::Error> for ExportError {
+    fn from(error: crate::Error) -> Self {
+        return ExportError::Http(error);
+    }
+}
+
+impl From<reqwest::Error> for ExportError {
+    fn from(error: reqwest::Error) -> Self {
+        return ExportError::Http(error.into());
+    }
+}
+
+impl From<serde_json::Error> for ExportError {
+    fn from(error: serde_json::Error) -> Self {
+        return ExportError::Json(error);
+    }
+}
+
+impl From<csv::Error> for ExportError {
+    fn from(error: csv::Error) -> Self {
+        return ExportError::Write(Box::new(error));
+    }
+}
+
+impl From<io::Error> for ExportError {
+    fn from(error: io::Error) -> Self {
+        return ExportError::Write(Box::new(error));
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl From<parquet::errors::ParquetError> for ExportError {
+    fn from(error: parquet::errors::ParquetError) -> Self {
+        return ExportError::Write(Box::new(error));
+    }
+}
+
+/// Output format of the invoice export, receiving one page of rows at a time.
+trait ExportSink {
+    fn write_rows(&mut self, rows: &[Vec<Option<String>>]) -> Result<(), ExportError>;
+
+    fn finish(self) -> Result<(), ExportError>;
+}
+
+struct CsvSink<W: io::Write> {
+    writer: csv::Writer<W>,
+}
+
+impl<W: io::Write> ExportSink for CsvSink<W> {
+    fn write_rows(&mut self, rows: &[Vec<Option<String>>]) -> Result<(), ExportError> {
+        for row in rows {
+            self.writer
+                .write_record(row.iter().map(|value| value.as_deref().unwrap_or_default()))?;
+        }
+        return Ok(());
+    }
+
+    fn finish(mut self) -> Result<(), ExportError> {
+        self.writer.flush()?;
+        return Ok(());
+    }
+}
+
+#[cfg(feature = "parquet")]
+struct ParquetSink<W: io::Write + Send> {
+    writer: parquet::file::writer::SerializedFileWriter<W>,
+    columns: usize,
+}
+
+#[cfg(feature = "parquet")]
+impl<W: io::Write + Send> ExportSink for ParquetSink<W> {
+    fn write_rows(&mut self, rows: &[Vec<Option<String>>]) -> Result<(), ExportError> {
+        use parquet::data_type::{ByteArray, ByteArrayType};
+
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let mut row_group = self.writer.next_row_group()?;
+        for index in 0..self.columns {
+            let mut values = Vec::new();
+            let mut def_levels = Vec::new();
+            for row in rows {
+                match &row[index] {
+                    Some(value) => {
+                        values.push(ByteArray::from(value.as_str()));
+                        def_levels.push(1);
+                    }
+                    None => def_levels.push(0),
+                }
+            }
+            if let Some(mut column) = row_group.next_column()? {
+                column
+                    .typed::<ByteArrayType>()
+                    .write_batch(&values, Some(&def_levels), None)?;
+                column.close()?;
+            }
+        }
+        row_group.close()?;
+        return Ok(());
+    }
+
+    fn finish(self) -> Result<(), ExportError> {
+        self.writer.close()?;
+        return Ok(());
+    }
+}
+
+impl SellAppClient {
+    /// Export every invoice matching ``config`` as CSV, with a header row.
+    ///
+    /// Invoices are fetched and written one page at a time, so the whole export is never held in memory.
+    /// Returns the amount of exported invoices.
+    ///
+    /// ```no_run
+    /// use sellapp::{export::ExportConfig, timestamp::Timestamp};
+    ///
+    /// # async fn run() -> Result<(), sellapp::export::ExportError> {
+    /// let sellapp_api = sellapp::init("your_api_key", "");
+    /// let config = ExportConfig {
+    ///     created_from: Timestamp::parse("2024-08-01").ok(),
+    ///     created_to: Timestamp::parse("2024-09-01").ok(),
+    ///     ..Default::default()
+    /// };
+    /// let file = std::fs::File::create("invoices-2024-08.csv")?;
+    /// sellapp_api.export_invoices_csv(&config, file).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn export_invoices_csv<W: io::Write>(
+        &self,
+        config: &ExportConfig,
+        writer: W,
+    ) -> Result<usize, ExportError> {
+        let mut writer = csv::Writer::from_writer(writer);
+        writer.write_record(config.columns.iter().map(Column::name))?;
+        return self.export_invoices(config, CsvSink { writer }).await;
+    }
+
+    /// Export every invoice matching ``config`` as a Parquet file, with every column stored as an optional UTF-8 string.
+    ///
+    /// Every page of invoices is written as its own row group, so the whole export is never held in memory.
+    /// Returns the amount of exported invoices.
+    #[cfg(feature = "parquet")]
+    pub async fn export_invoices_parquet<W: io::Write + Send>(
+        &self,
+        config: &ExportConfig,
+        writer: W,
+    ) -> Result<usize, ExportError> {
+        use parquet::{
+            basic::{ConvertedType, Repetition, Type as PhysicalType},
+            file::{properties::WriterProperties, writer::SerializedFileWriter},
+            schema::types::Type,
+        };
+        use std::sync::Arc;
+
+        let mut fields = Vec::new();
+        for column in &config.columns {
+            let field = Type::primitive_type_builder(column.name(), PhysicalType::BYTE_ARRAY)
+                .with_repetition(Repetition::OPTIONAL)
+                .with_converted_type(ConvertedType::UTF8)
+                .build()?;
+            fields.push(Arc::new(field));
+        }
+        let schema = Type::group_type_builder("invoice")
+            .with_fields(fields)
+            .build()?;
+        let writer = SerializedFileWriter::new(
+            writer,
+            Arc::new(schema),
+            Arc::new(WriterProperties::builder().build()),
+        )?;
+
+        let sink = ParquetSink {
+            writer,
+            columns: config.columns.len(),
+        };
+        return self.export_invoices(config, sink).await;
+    }
+
+    async fn export_invoices(
+        &self,
+        config: &ExportConfig,
+        mut sink: impl ExportSink,
+    ) -> Result<usize, ExportError> {
+        let extra_params = config.to_query().to_extra_params();
+        let mut exported = 0;
+        for page in 1.. {
+            let items = self
+                .list_page(Resource::Invoices, page, &extra_params)
+                .await?;
+            let last_page = items.len() < PAGE_SIZE;
+
+            let mut rows = Vec::new();
+            for item in items {
+                let invoice: Invoice = serde_json::from_value(item)?;
+                if config.includes(&invoice) {
+                    rows.push(
+                        config
+                            .columns
+                            .iter()
+                            .map(|column| column.value(&invoice))
+                            .collect(),
+                    );
+                }
+            }
+            exported += rows.len();
+            sink.write_rows(&rows)?;
+
+            if last_page {
+                break;
+            }
+        }
+        sink.finish()?;
+        return Ok(exported);
+    }
+}
@@ -0,0 +1,231 @@
+use std::fmt;
+
+use crate::Error;
+use serde_json::{json, Value};
+
+use crate::{
+    resource::{Resource, PAGE_SIZE, SORT_BY_CREATED_DESC},
+    util::unwrap_data,
+    SellAppClient,
+};
+
+/// Key in the invoice ``metadata`` under which the idempotency key is stored.
+pub const IDEMPOTENCY_KEY_FIELD: &str = "idempotency_key";
+
+/// How many pages of the most recent invoices are searched for an existing idempotency key.
+pub const IDEMPOTENCY_LOOKBACK_PAGES: u32 = 5;
+
+/// Outcome of ``SellAppClient::invoices_create_idempotent``.
+#[derive(Debug, Clone)]
+pub enum InvoiceCreation {
+    /// No invoice with the key existed, and a new one was created.
+    Created(Value),
+    /// An invoice with the key already existed (e.g. from a request that timed out), and was returned instead.
+    Recovered(Value),
+}
+
+impl InvoiceCreation {
+    /// The created or recovered invoice.
+    pub fn invoice(&self) -> &Value {
+        return match self {
+            InvoiceCreation::Created(invoice) => invoice,
+            InvoiceCreation::Recovered(invoice) => invoice,
+        };
+    }
+
+    pub fn is_recovered(&self) -> bool {
+        return matches!(self, InvoiceCreation::Recovered(_));
+    }
+}
+
+/// Error returned by ``SellAppClient::invoices_create_idempotent``.
+#[derive(Debug)]
+pub enum IdempotencyError {
+    /// Calling the API failed.
+    Http(Error),
+    /// Creating the invoice failed, and so did searching for an invoice it may have created anyway. Whether the
+    /// invoice exists is unknown, so retry with the same key.
+    Unresolved { create: Error, lookup: Error },
+}
+
+impl fmt::Display for IdempotencyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            IdempotencyError::Http(error) => write!(f, "request failed: {}", error),
+            IdempotencyError::Unresolved { create, lookup } => write!(
+                f,
+                "failed to create invoice: {} (searching for it afterwards failed too: {})",
+                create, lookup
+            ),
+        };
+    }
+}
+
+impl std::error::Error for IdempotencyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        return match self {
+            IdempotencyError::Http(error) => Some(error),
+            IdempotencyError::Unresolved { create, .. } => Some(create),
+        };
+    }
+}
+
+impl From<Error> for IdempotencyError {
+    fn from(error: Error) -> Self {
+        return IdempotencyError::Http(error);
+    }
+}
+
+impl From<reqwest::Error> for IdempotencyError {
+    fn from(error: reqwest::Error) -> Self {
+        return IdempotencyError::Http(error.into());
+    }
+}
+
+impl SellAppClient {
+    /// Create a new invoice at most once per ``idempotency_key``.
+    ///
+    /// The key is stored in the ``metadata`` of the invoice. Before creating the invoice, and again if creating it fails
+    /// with a network or server error, the most recent invoices are searched for the key, so retrying after a timeout
+    /// returns the invoice that was already created instead of charging the customer twice.
+    ///
+    /// **Note**: The API has no idempotency support of its own, so this only protects sequential retries. Two calls
+    /// with the same key running at the same time can both miss the other's invoice and both create one.
+    ///
+    /// If creating the invoice fails and the search afterwards fails too, ``IdempotencyError::Unresolved`` holds both
+    /// errors, as the invoice may or may not exist.
+    ///
+    /// ``idempotency_key``: A unique key generated by you for this invoice, e.g. your internal order ID, required.
+    ///
+    /// ``data``: JSON with the invoice data, required.
+    ///
+    /// https://developer.sell.app/invoices-v2#create-an-invoice
+    pub async fn invoices_create_idempotent(
+        &self,
+        idempotency_key: &str,
+        mut data: Value,
+    ) -> Result<InvoiceCreation, IdempotencyError> {
+        if let Some(invoice) = self
+            .invoices_find_by_idempotency_key(idempotency_key)
+            .await?
+        {
+            return Ok(InvoiceCreation::Recovered(invoice));
+        }
+
+        if let Some(fields) = data.as_object_mut() {
+            let metadata = fields.entry("metadata").or_insert_with(|| json!({}));
+            if let Some(metadata) = metadata.as_object_mut() {
+                metadata.insert(IDEMPOTENCY_KEY_FIELD.to_string(), json!(idempotency_key));
+            }
+        }
+
+        let res = self
+            .invoices_create(data.to_string())
+            .await
+            .and_then(|res| Ok(res.error_for_status()?));
+        match res {
+            Ok(res) => {
+                let invoice = res.json::<Value>().await?;
+                return Ok(InvoiceCreation::Created(unwrap_data(invoice)));
+            }
+            Err(error) => {
+                let client_error = error
+                    .status()
+                    .is_some_and(|status| status.is_client_error());
+                if client_error {
+                    return Err(IdempotencyError::Http(error));
+                }
+                return match self.invoices_find_by_idempotency_key(idempotency_key).await {
+                    Ok(Some(invoice)) => Ok(InvoiceCreation::Recovered(invoice)),
+                    Ok(None) => Err(IdempotencyError::Http(error)),
+                    Err(lookup) => Err(IdempotencyError::Unresolved {
+                        create: error,
+                        lookup,
+                    }),
+                };
+            }
+        }
+    }
+
+    /// Search the most recent invoices for one created with ``idempotency_key``.
+    ///
+    /// Invoices are searched newest first, up to ``IDEMPOTENCY_LOOKBACK_PAGES`` pages of ``resource::PAGE_SIZE`` invoices.
+    pub async fn invoices_find_by_idempotency_key(
+        &self,
+        idempotency_key: &str,
+    ) -> Result<Option<Value>, Error> {
+        let client = self.uncached();
+        for page in 1..=IDEMPOTENCY_LOOKBACK_PAGES {
+            let invoices = client
+                .list_page(Resource::Invoices, page, SORT_BY_CREATED_DESC)
+                .await?;
+
+            let found = invoices
+                .iter()
+                .find(|invoice| invoice["metadata"][IDEMPOTENCY_KEY_FIELD] == idempotency_key);
+            if let Some(invoice) = found {
+                return Ok(Some(invoice.clone()));
+            }
+            if invoices.len() < PAGE_SIZE {
+                break;
+            }
+        }
+        return Ok(None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::transport::mock::{self, MockTransport};
+
+    /// A store without invoices, where creating an invoice fails with ``create_status`` and every search after the
+    /// first one fails with ``lookup_status``.
+    fn store(create_status: u16, lookup_status: u16) -> MockTransport {
+        let lookups = AtomicUsize::new(0);
+        return MockTransport::new(move |line, _| {
+            if line.starts_with("POST v2/invoices") {
+                return mock::json(create_status, json!({ "message": "create failed" }));
+            }
+            return match lookups.fetch_add(1, Ordering::SeqCst) {
+                0 => mock::json(200, json!({ "data": [] })),
+                _ => mock::json(lookup_status, json!({ "message": "lookup failed" })),
+            };
+        });
+    }
+
+    #[tokio::test]
+    async fn keeps_the_create_error_when_the_lookup_fails() {
+        let transport = store(502, 503);
+        let client = mock::client(&transport);
+        let error = client
+            .invoices_create_idempotent("order-1", json!({}))
+            .await
+            .unwrap_err();
+        match error {
+            IdempotencyError::Unresolved { create, lookup } => {
+                assert_eq!(create.status().unwrap(), 502);
+                assert_eq!(lookup.status().unwrap(), 503);
+            }
+            error => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[tokio::test]
+    async fn returns_client_errors_without_searching() {
+        let transport = store(422, 503);
+        let client = mock::client(&transport);
+        let error = client
+            .invoices_create_idempotent("order-1", json!({}))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, IdempotencyError::Http(error) if error.status().unwrap() == 422));
+        assert_eq!(transport.count("GET v2/invoices"), 1);
+        assert_eq!(
+            transport.bodies("POST v2/invoices")[0]["metadata"][IDEMPOTENCY_KEY_FIELD],
+            "order-1"
+        );
+    }
+}
@@ -1,13 +1,85 @@
+#![allow(clippy::needless_return)]
+
+use reqwest::Request;
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::ResponseBuilderExt;
 use reqwest::{
     self,
     header::{HeaderMap, HeaderValue},
-    Client, Error, Method, Response,
+    Client, Method, RequestBuilder, Response,
 };
+use serde::de::DeserializeOwned;
+use std::{any::Any, collections::HashMap, sync::Arc};
+
+pub mod analytics;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod audit;
+pub mod backup;
+pub mod bulk;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cache;
+#[cfg(feature = "catalog")]
+pub mod catalog;
+pub mod checkout;
+pub mod copy;
+pub mod deliverable;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod dry_run;
+#[cfg(feature = "dynamic")]
+pub mod dynamic;
+mod error;
+#[cfg(feature = "export")]
+pub mod export;
+pub mod idempotency;
+pub mod middleware;
+pub mod models;
+pub mod money;
+pub mod replacement;
+pub mod resource;
+pub mod routes;
+mod rt;
+pub mod stock;
+#[cfg(feature = "sync")]
+pub mod sync;
+#[cfg(feature = "metrics")]
+mod telemetry;
+pub mod timestamp;
+pub mod transport;
+mod util;
+pub mod watcher;
+
+pub use error::Error;
+
+#[cfg(not(target_arch = "wasm32"))]
+use audit::AuditLog;
+#[cfg(not(target_arch = "wasm32"))]
+use cache::{CacheLookup, ResponseCache};
+#[cfg(not(target_arch = "wasm32"))]
+use dry_run::DryRun;
+use middleware::{Middleware, Next, RateLimit, RequestContext};
+use resource::Resource;
+use routes::ApiVersion;
+use rt::Instant;
+use transport::HttpTransport;
 
 pub struct SellAppClient {
     api_key: String,
     store_slug: String,
     http_client: Client,
+    transport: Arc<dyn HttpTransport>,
+    middlewares: Vec<Arc<dyn Middleware>>,
+    /// The ``RateLimit`` middlewares among ``middlewares``, so bulk runs sharing one don't take two slots per call.
+    rate_limits: Vec<RateLimit>,
+    #[cfg(not(target_arch = "wasm32"))]
+    cache: Option<Arc<ResponseCache>>,
+    /// Skip cache lookups, set on the copies returned by ``uncached``.
+    #[cfg(not(target_arch = "wasm32"))]
+    bypass_cache: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    audit: Option<Arc<AuditLog>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    operator: Option<String>,
+    api_versions: HashMap<Resource, ApiVersion>,
 }
 
 impl SellAppClient {
@@ -31,22 +103,31 @@ impl SellAppClient {
         return headers;
     }
 
-    async fn send_request(&self, url: String, method: Method) -> Result<Response, Error> {
+    /// Build the path of a route with the API version used for ``resource``.
+    fn route(&self, resource: Resource, rest: &str) -> String {
+        return routes::route(self.api_version(resource), resource, rest);
+    }
+
+    async fn send_request(
+        &self,
+        operation: &'static str,
+        url: String,
+        method: Method,
+    ) -> Result<Response, Error> {
         let req_headers = self.generate_headers(vec![["Accept", "application/json"]]);
         let req_url = format!("https://sell.app/api/{}", url);
 
         let req = self
             .http_client
             .request(method, req_url)
-            .headers(req_headers)
-            .send()
-            .await;
+            .headers(req_headers);
 
-        return req;
+        return self.execute(operation, req).await;
     }
 
     async fn send_request_data(
         &self,
+        operation: &'static str,
         url: String,
         method: Method,
         body: String,
@@ -61,11 +142,351 @@ impl SellAppClient {
             .http_client
             .request(method, req_url)
             .headers(req_headers)
-            .body(body)
-            .send()
-            .await;
+            .body(body);
+
+        return self.execute(operation, req).await;
+    }
+
+    async fn execute(
+        &self,
+        operation: &'static str,
+        req: RequestBuilder,
+    ) -> Result<Response, Error> {
+        let request = req.build()?;
+        let ctx = RequestContext {
+            operation,
+            method: request.method().clone(),
+            url: request.url().clone(),
+            started: Instant::now(),
+        };
+        return Next::new(&ctx, &self.middlewares, self).run(request).await;
+    }
+
+    /// Send a request once every middleware ran, through the audit log and cache if enabled.
+    async fn dispatch(&self, ctx: &RequestContext, request: Request) -> Result<Response, Error> {
+        #[cfg(not(target_arch = "wasm32"))]
+        let audit = self.audit.as_ref().filter(|_| ctx.method != Method::GET);
+        #[cfg(not(target_arch = "wasm32"))]
+        let audit_body = audit.and_then(|_| request.body()?.as_bytes().map(<[u8]>::to_vec));
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let res = match &self.cache {
+            Some(cache) => self.execute_cached(cache, request).await,
+            None => self.transport.execute(request).await.map_err(Error::from),
+        };
+        #[cfg(target_arch = "wasm32")]
+        let res = self.transport.execute(request).await.map_err(Error::from);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(audit) = audit {
+            return self.execute_audited(audit, ctx, audit_body, res).await;
+        }
+        #[cfg(target_arch = "wasm32")]
+        let _ = ctx;
+        return res;
+    }
+
+    /// Record a mutating call in the audit log. The response body is buffered, so the caller still gets a readable response.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn execute_audited(
+        &self,
+        audit: &AuditLog,
+        ctx: &RequestContext,
+        body: Option<Vec<u8>>,
+        res: Result<Response, Error>,
+    ) -> Result<Response, Error> {
+        let operator = self.operator.as_deref();
+        let response = match res {
+            Ok(response) => response,
+            Err(error) => {
+                let record = audit::audit_record(ctx, operator, body.as_deref(), Err(&error));
+                audit.write(record, &self.api_key);
+                return Err(error);
+            }
+        };
+
+        let mut builder = http::Response::builder()
+            .status(response.status())
+            .version(response.version())
+            .url(response.url().clone());
+        if let Some(headers) = builder.headers_mut() {
+            headers.extend(response.headers().clone());
+        }
+        let status = response.status();
+        let bytes = match response.bytes().await.map_err(Error::from) {
+            Ok(bytes) => bytes,
+            Err(error) => {
+                let record = audit::audit_record(ctx, operator, body.as_deref(), Err(&error));
+                audit.write(record, &self.api_key);
+                return Err(error);
+            }
+        };
+
+        let record = audit::audit_record(ctx, operator, body.as_deref(), Ok((status, &bytes)));
+        audit.write(record, &self.api_key);
+        return Ok(Response::from(builder.body(bytes).unwrap()));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn execute_cached(
+        &self,
+        cache: &ResponseCache,
+        mut request: Request,
+    ) -> Result<Response, Error> {
+        let stale = match self.bypass_cache {
+            true => None,
+            false => match cache.lookup(&mut request) {
+                CacheLookup::Fresh(response) => return Ok(response),
+                CacheLookup::Stale(entry) => Some(entry),
+                CacheLookup::Miss => None,
+            },
+        };
+        let method = request.method().clone();
+        let key = cache::cache_key(&request);
+
+        let response = self.transport.execute(request).await?;
+        return Ok(cache.store(&method, key, stale, response).await?);
+    }
+
+    /// Send requests with a different HTTP stack, see ``transport::HttpTransport`` for details.
+    ///
+    /// Replaces the transport of this client without changing its type, so it can be done at any time, and copies
+    /// made with ``for_store`` afterwards share the new transport.
+    ///
+    /// A ``reqwest::Client`` with its own TLS or proxy settings can be used directly.
+    ///
+    /// ```
+    /// let http_client = reqwest::Client::builder().user_agent("my-app/1.0").build().unwrap();
+    /// let sellapp_api = sellapp::init("your_api_key", "").with_transport(http_client);
+    /// ```
+    pub fn with_transport(mut self, transport: impl HttpTransport + 'static) -> Self {
+        self.transport = Arc::new(transport);
+        return self;
+    }
+
+    /// Add a middleware that runs around every request sent by this client.
+    ///
+    /// Middlewares run in the order they were added, see ``middleware::Middleware`` for details.
+    ///
+    /// ```
+    /// struct MyMiddleware;
+    /// impl sellapp::middleware::Middleware for MyMiddleware {}
+    ///
+    /// let sellapp_api = sellapp::init("your_api_key", "").with_middleware(MyMiddleware);
+    /// ```
+    pub fn with_middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        if let Some(rate_limit) = (&middleware as &dyn Any).downcast_ref::<RateLimit>() {
+            self.rate_limits.push(rate_limit.clone());
+        }
+        self.middlewares.push(Arc::new(middleware));
+        return self;
+    }
+
+    /// Cache the responses of GET endpoints, see ``cache::ResponseCache`` for details.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use sellapp::cache::ResponseCache;
+    ///
+    /// let cache = ResponseCache::new(Duration::from_secs(60)).with_ttl("products", Duration::from_secs(300));
+    /// let sellapp_api = sellapp::init("your_api_key", "").with_cache(cache);
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = Some(Arc::new(cache));
+        return self;
+    }
+
+    /// Record every mutating call of this client, see ``audit::AuditLog`` for details.
+    ///
+    /// ```no_run
+    /// use sellapp::audit::{AuditLog, JsonlAuditSink};
+    ///
+    /// let audit = AuditLog::new(JsonlAuditSink::open("audit.jsonl").unwrap())
+    ///     .with_redact_api_key(true)
+    ///     .with_redact_pii(true);
+    /// let sellapp_api = sellapp::init("your_api_key", "")
+    ///     .with_audit(audit)
+    ///     .with_operator("jane@example.com");
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_audit(mut self, audit: AuditLog) -> Self {
+        self.audit = Some(Arc::new(audit));
+        return self;
+    }
 
-        return req;
+    /// Label the person or system using this client, added to every audit record.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_operator(mut self, operator: &str) -> Self {
+        self.operator = Some(operator.to_string());
+        return self;
+    }
+
+    /// Block every mutating call (creates, updates, deletes, replacements, ticket replies, ...) and record it
+    /// in ``dry_run`` instead, while GET calls still reach the API. See ``dry_run::DryRun`` for what blocked calls return.
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), sellapp::Error> {
+    /// use sellapp::dry_run::DryRun;
+    ///
+    /// let dry_run = DryRun::new();
+    /// let sellapp_api = sellapp::init("your_api_key", "").with_dry_run(&dry_run);
+    /// sellapp_api.products_delete("12".to_string()).await?;
+    /// for entry in dry_run.entries() {
+    ///     println!("{}", entry);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_dry_run(self, dry_run: &DryRun) -> Self {
+        return self.with_middleware(dry_run.clone());
+    }
+
+    /// Whether ``rate_limit`` (or a clone of it) is installed as a middleware of this client.
+    pub(crate) fn has_rate_limit(&self, rate_limit: &RateLimit) -> bool {
+        return self
+            .rate_limits
+            .iter()
+            .any(|installed| installed.same_bucket(rate_limit));
+    }
+
+    /// Get a copy of this client that accesses a different store, keeping its middlewares and cache.
+    ///
+    /// ``store_slug``: The slug of the store to access, or an empty &str for the first store from your storefront list.
+    pub fn for_store(&self, store_slug: &str) -> SellAppClient {
+        return SellAppClient {
+            api_key: self.api_key.clone(),
+            store_slug: store_slug.to_string(),
+            http_client: self.http_client.clone(),
+            transport: self.transport.clone(),
+            middlewares: self.middlewares.clone(),
+            rate_limits: self.rate_limits.clone(),
+            #[cfg(not(target_arch = "wasm32"))]
+            cache: self.cache.clone(),
+            #[cfg(not(target_arch = "wasm32"))]
+            bypass_cache: self.bypass_cache,
+            #[cfg(not(target_arch = "wasm32"))]
+            audit: self.audit.clone(),
+            #[cfg(not(target_arch = "wasm32"))]
+            operator: self.operator.clone(),
+            api_versions: self.api_versions.clone(),
+        };
+    }
+
+    /// Get a copy of this client whose GET calls always reach the API, for helpers that act on the current state
+    /// of the store. Their responses still refresh the cache, and their writes still invalidate it.
+    pub(crate) fn uncached(&self) -> SellAppClient {
+        #[allow(unused_mut)]
+        let mut client = self.for_store(&self.store_slug);
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            client.bypass_cache = true;
+        }
+        return client;
+    }
+
+    /// Use a different API version for the endpoints of ``resource``, e.g. the v1 invoice endpoints for integrations
+    /// that depend on their response shape. See ``routes::supported_versions`` for the available versions.
+    ///
+    /// The helpers built on top of the endpoints, like ``models::Invoice`` and ``checkout``, expect the default version.
+    ///
+    /// **Panics** if ``resource`` isn't available in ``version``, as every call to it would fail.
+    ///
+    /// ```
+    /// use sellapp::{resource::Resource, routes::ApiVersion};
+    ///
+    /// let sellapp_api = sellapp::init("your_api_key", "").with_api_version(Resource::Invoices, ApiVersion::V1);
+    /// assert_eq!(sellapp_api.api_version(Resource::Invoices), ApiVersion::V1);
+    /// ```
+    ///
+    /// ```should_panic
+    /// use sellapp::{resource::Resource, routes::ApiVersion};
+    ///
+    /// // Groups are only available in v2.
+    /// let sellapp_api = sellapp::init("your_api_key", "").with_api_version(Resource::Groups, ApiVersion::V1);
+    /// ```
+    pub fn with_api_version(mut self, resource: Resource, version: ApiVersion) -> Self {
+        let supported = routes::supported_versions(resource);
+        if !supported.contains(&version) {
+            let supported: Vec<&str> = supported.iter().map(ApiVersion::prefix).collect();
+            panic!(
+                "the {} endpoints are not available in API {}, only in {}",
+                resource.name(),
+                version.prefix(),
+                supported.join(", ")
+            );
+        }
+        self.api_versions.insert(resource, version);
+        return self;
+    }
+
+    /// Get the API version used for the endpoints of ``resource``.
+    pub fn api_version(&self, resource: Resource) -> ApiVersion {
+        return match self.api_versions.get(&resource) {
+            Some(version) => *version,
+            None => routes::default_version(resource),
+        };
+    }
+
+    /// Call any endpoint of the API, for endpoints this crate doesn't cover yet.
+    ///
+    /// The request goes through the same authentication, store header, middlewares, cache and audit log as the
+    /// other methods, with **"request"** as the operation name. Returns the response regardless of its status code.
+    ///
+    /// ``method``: The HTTP method, e.g. ``Method::GET``.
+    ///
+    /// ``path``: The path after **"https://sell.app/api/"**, e.g. **"v2/products"**.
+    ///
+    /// ``url_params``: Optional attributes to append to the request URL, e.g. **"?limit=50&page=1"**
+    ///
+    /// ``body``: Optional JSON body of the request.
+    pub async fn request_raw(
+        &self,
+        method: Method,
+        path: &str,
+        url_params: &str,
+        body: Option<String>,
+    ) -> Result<Response, Error> {
+        let url = format!("{}{}", path.trim_start_matches('/'), url_params);
+        return match body {
+            Some(body) => self.send_request_data("request", url, method, body).await,
+            None => self.send_request("request", url, method).await,
+        };
+    }
+
+    /// Call any endpoint of the API and deserialize the response, see ``request_raw``.
+    ///
+    /// Error status codes are returned as errors, like ``Response::error_for_status`` does. Most endpoints wrap
+    /// their result in a **"data"** attribute, which ``T`` has to account for.
+    ///
+    /// ```no_run
+    /// use reqwest::Method;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Data<T> {
+    ///     data: T,
+    /// }
+    ///
+    /// # async fn run() -> Result<(), sellapp::Error> {
+    /// let sellapp_api = sellapp::init("your_api_key", "");
+    /// let products = sellapp_api
+    ///     .request::<Data<Vec<serde_json::Value>>>(Method::GET, "v2/products", "?limit=10", None)
+    ///     .await?
+    ///     .data;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn request<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        url_params: &str,
+        body: Option<String>,
+    ) -> Result<T, Error> {
+        let res = self.request_raw(method, path, url_params, body).await?;
+        return Ok(res.error_for_status()?.json::<T>().await?);
     }
 
     /// Fetch all of your blacklist rules.
@@ -75,7 +496,11 @@ impl SellAppClient {
     /// https://developer.sell.app/blacklists#list-all-blacklist-rules
     pub async fn blacklist_list_all_rules(&self, url_params: &str) -> Result<Response, Error> {
         return self
-            .send_request(format!("v1/blacklists{}", url_params), Method::GET)
+            .send_request(
+                "blacklist_list_all_rules",
+                self.route(Resource::Blacklists, url_params),
+                Method::GET,
+            )
             .await;
     }
 
@@ -86,7 +511,12 @@ impl SellAppClient {
     /// https://developer.sell.app/blacklists#create-a-blacklist-rule
     pub async fn blacklist_create_rule(&self, data: String) -> Result<Response, Error> {
         return self
-            .send_request_data("v1/blacklists".to_string(), Method::POST, data)
+            .send_request_data(
+                "blacklist_create_rule",
+                self.route(Resource::Blacklists, ""),
+                Method::POST,
+                data,
+            )
             .await;
     }
 
@@ -97,7 +527,11 @@ impl SellAppClient {
     /// https://developer.sell.app/blacklists#retrieve-a-blacklist-rule
     pub async fn blacklist_get_rule(&self, rule_id: String) -> Result<Response, Error> {
         return self
-            .send_request(format!("v1/blacklists/{}", rule_id), Method::GET)
+            .send_request(
+                "blacklist_get_rule",
+                self.route(Resource::Blacklists, &format!("/{}", rule_id)),
+                Method::GET,
+            )
             .await;
     }
 
@@ -114,7 +548,12 @@ impl SellAppClient {
         data: String,
     ) -> Result<Response, Error> {
         return self
-            .send_request_data(format!("v1/blacklists/{}", rule_id), Method::PATCH, data)
+            .send_request_data(
+                "blacklist_update_rule",
+                self.route(Resource::Blacklists, &format!("/{}", rule_id)),
+                Method::PATCH,
+                data,
+            )
             .await;
     }
 
@@ -125,7 +564,11 @@ impl SellAppClient {
     /// https://developer.sell.app/blacklists#delete-a-blacklist-rule
     pub async fn blacklist_delete_rule(&self, rule_id: String) -> Result<Response, Error> {
         return self
-            .send_request(format!("v1/blacklists/{}", rule_id), Method::DELETE)
+            .send_request(
+                "blacklist_delete_rule",
+                self.route(Resource::Blacklists, &format!("/{}", rule_id)),
+                Method::DELETE,
+            )
             .await;
     }
 
@@ -136,7 +579,11 @@ impl SellAppClient {
     /// https://developer.sell.app/coupons#list-all-coupons
     pub async fn coupons_list_all(&self, url_params: &str) -> Result<Response, Error> {
         return self
-            .send_request(format!("v1/coupons{}", url_params), Method::GET)
+            .send_request(
+                "coupons_list_all",
+                self.route(Resource::Coupons, url_params),
+                Method::GET,
+            )
             .await;
     }
 
@@ -147,7 +594,12 @@ impl SellAppClient {
     /// https://developer.sell.app/coupons#create-a-coupon
     pub async fn coupons_create(&self, data: String) -> Result<Response, Error> {
         return self
-            .send_request_data("v1/coupons".to_owned(), Method::POST, data)
+            .send_request_data(
+                "coupons_create",
+                self.route(Resource::Coupons, ""),
+                Method::POST,
+                data,
+            )
             .await;
     }
 
@@ -158,7 +610,11 @@ impl SellAppClient {
     /// https://developer.sell.app/coupons#retrieve-a-coupon
     pub async fn coupons_get(&self, coupon_id: String) -> Result<Response, Error> {
         return self
-            .send_request(format!("v1/coupons/{}", coupon_id), Method::GET)
+            .send_request(
+                "coupons_get",
+                self.route(Resource::Coupons, &format!("/{}", coupon_id)),
+                Method::GET,
+            )
             .await;
     }
 
@@ -171,7 +627,12 @@ impl SellAppClient {
     /// https://developer.sell.app/coupons#update-a-coupon
     pub async fn coupons_update(&self, coupon_id: String, data: String) -> Result<Response, Error> {
         return self
-            .send_request_data(format!("v1/coupons/{}", coupon_id), Method::PATCH, data)
+            .send_request_data(
+                "coupons_update",
+                self.route(Resource::Coupons, &format!("/{}", coupon_id)),
+                Method::PATCH,
+                data,
+            )
             .await;
     }
 
@@ -182,7 +643,11 @@ impl SellAppClient {
     /// https://developer.sell.app/coupons#delete-a-coupon
     pub async fn coupons_delete(&self, coupon_id: String) -> Result<Response, Error> {
         return self
-            .send_request(format!("v1/coupons/{}", coupon_id), Method::DELETE)
+            .send_request(
+                "coupons_delete",
+                self.route(Resource::Coupons, &format!("/{}", coupon_id)),
+                Method::DELETE,
+            )
             .await;
     }
 
@@ -193,7 +658,11 @@ impl SellAppClient {
     /// https://developer.sell.app/feedback#list-all-feedback
     pub async fn feedback_list_all(&self, url_params: &str) -> Result<Response, Error> {
         return self
-            .send_request(format!("v1/feedback{}", url_params), Method::GET)
+            .send_request(
+                "feedback_list_all",
+                self.route(Resource::Feedback, url_params),
+                Method::GET,
+            )
             .await;
     }
 
@@ -204,7 +673,11 @@ impl SellAppClient {
     /// https://developer.sell.app/feedback#retrieve-specific-feedback
     pub async fn feedback_get(&self, feedback_id: String) -> Result<Response, Error> {
         return self
-            .send_request(format!("v1/feedback/{}", feedback_id), Method::GET)
+            .send_request(
+                "feedback_get",
+                self.route(Resource::Feedback, &format!("/{}", feedback_id)),
+                Method::GET,
+            )
             .await;
     }
 
@@ -221,7 +694,12 @@ impl SellAppClient {
         data: String,
     ) -> Result<Response, Error> {
         return self
-            .send_request_data(format!("v1/coupons/{}", feedback_id), Method::PATCH, data)
+            .send_request_data(
+                "feedback_reply",
+                self.route(Resource::Feedback, &format!("/{}", feedback_id)),
+                Method::PATCH,
+                data,
+            )
             .await;
     }
 
@@ -232,7 +710,11 @@ impl SellAppClient {
     /// https://developer.sell.app/groups#list-all-groups
     pub async fn groups_list_all(&self, url_params: &str) -> Result<Response, Error> {
         return self
-            .send_request(format!("v2/groups{}", url_params), Method::GET)
+            .send_request(
+                "groups_list_all",
+                self.route(Resource::Groups, url_params),
+                Method::GET,
+            )
             .await;
     }
 
@@ -243,7 +725,12 @@ impl SellAppClient {
     /// https://developer.sell.app/groups#create-a-group
     pub async fn groups_create(&self, data: String) -> Result<Response, Error> {
         return self
-            .send_request_data("v2/groups".to_string(), Method::POST, data)
+            .send_request_data(
+                "groups_create",
+                self.route(Resource::Groups, ""),
+                Method::POST,
+                data,
+            )
             .await;
     }
 
@@ -254,7 +741,11 @@ impl SellAppClient {
     /// https://developer.sell.app/groups#retrieve-a-group
     pub async fn groups_get(&self, group_id: String) -> Result<Response, Error> {
         return self
-            .send_request(format!("v2/groups/{}", group_id), Method::GET)
+            .send_request(
+                "groups_get",
+                self.route(Resource::Groups, &format!("/{}", group_id)),
+                Method::GET,
+            )
             .await;
     }
 
@@ -267,7 +758,12 @@ impl SellAppClient {
     /// https://developer.sell.app/groups#update-a-group
     pub async fn groups_update(&self, group_id: String, data: String) -> Result<Response, Error> {
         return self
-            .send_request_data(format!("v2/groups/{}", group_id), Method::PATCH, data)
+            .send_request_data(
+                "groups_update",
+                self.route(Resource::Groups, &format!("/{}", group_id)),
+                Method::PATCH,
+                data,
+            )
             .await;
     }
 
@@ -278,7 +774,11 @@ impl SellAppClient {
     /// https://developer.sell.app/groups#delete-a-group
     pub async fn groups_delete(&self, group_id: String) -> Result<Response, Error> {
         return self
-            .send_request(format!("v2/groups/{}", group_id), Method::DELETE)
+            .send_request(
+                "groups_delete",
+                self.route(Resource::Groups, &format!("/{}", group_id)),
+                Method::DELETE,
+            )
             .await;
     }
 
@@ -296,7 +796,8 @@ impl SellAppClient {
     ) -> Result<Response, Error> {
         return self
             .send_request_data(
-                format!("v2/groups/{}/products/attach", group_id),
+                "groups_add_products",
+                self.route(Resource::Groups, &format!("/{}/products/attach", group_id)),
                 Method::POST,
                 data,
             )
@@ -317,7 +818,8 @@ impl SellAppClient {
     ) -> Result<Response, Error> {
         return self
             .send_request_data(
-                format!("v2/groups/{}/products/detach", group_id),
+                "groups_remove_products",
+                self.route(Resource::Groups, &format!("/{}/products/detach", group_id)),
                 Method::DELETE,
                 data,
             )
@@ -338,7 +840,11 @@ impl SellAppClient {
     ) -> Result<Response, Error> {
         return self
             .send_request(
-                format!("v2/groups/{}/products{}", group_id, url_params),
+                "groups_list_products",
+                self.route(
+                    Resource::Groups,
+                    &format!("/{}/products{}", group_id, url_params),
+                ),
                 Method::GET,
             )
             .await;
@@ -358,7 +864,11 @@ impl SellAppClient {
     ) -> Result<Response, Error> {
         return self
             .send_request(
-                format!("v2/groups/{}/products/{}", group_id, product_id),
+                "groups_get_product",
+                self.route(
+                    Resource::Groups,
+                    &format!("/{}/products/{}", group_id, product_id),
+                ),
                 Method::GET,
             )
             .await;
@@ -371,7 +881,11 @@ impl SellAppClient {
     /// https://developer.sell.app/invoices-v2#list-all-invoices
     pub async fn invoices_list_all(&self, url_params: &str) -> Result<Response, Error> {
         return self
-            .send_request(format!("v2/invoices{}", url_params), Method::GET)
+            .send_request(
+                "invoices_list_all",
+                self.route(Resource::Invoices, url_params),
+                Method::GET,
+            )
             .await;
     }
 
@@ -382,7 +896,12 @@ impl SellAppClient {
     /// https://developer.sell.app/invoices-v2#create-an-invoice
     pub async fn invoices_create(&self, data: String) -> Result<Response, Error> {
         return self
-            .send_request_data("v2/invoices".to_string(), Method::POST, data)
+            .send_request_data(
+                "invoices_create",
+                self.route(Resource::Invoices, ""),
+                Method::POST,
+                data,
+            )
             .await;
     }
 
@@ -393,7 +912,11 @@ impl SellAppClient {
     /// https://developer.sell.app/invoices-v2#retrieve-an-invoice
     pub async fn invoices_get(&self, invoice_id: String) -> Result<Response, Error> {
         return self
-            .send_request(format!("v2/invoices/{}", invoice_id), Method::GET)
+            .send_request(
+                "invoices_get",
+                self.route(Resource::Invoices, &format!("/{}", invoice_id)),
+                Method::GET,
+            )
             .await;
     }
 
@@ -404,7 +927,11 @@ impl SellAppClient {
     /// https://developer.sell.app/invoices-v2#create-a-checkout-session
     pub async fn invoices_checkout(&self, invoice_id: String) -> Result<Response, Error> {
         return self
-            .send_request(format!("v2/invoices/{}/checkout", invoice_id), Method::POST)
+            .send_request(
+                "invoices_checkout",
+                self.route(Resource::Invoices, &format!("/{}/checkout", invoice_id)),
+                Method::POST,
+            )
             .await;
     }
 
@@ -416,7 +943,8 @@ impl SellAppClient {
     pub async fn invoices_get_items(&self, invoice_id: String) -> Result<Response, Error> {
         return self
             .send_request(
-                format!("v2/invoices/{}/deliverables", invoice_id),
+                "invoices_get_items",
+                self.route(Resource::Invoices, &format!("/{}/deliverables", invoice_id)),
                 Method::GET,
             )
             .await;
@@ -432,7 +960,11 @@ impl SellAppClient {
     pub async fn invoices_mark_completed(&self, invoice_id: String) -> Result<Response, Error> {
         return self
             .send_request(
-                format!("v2/invoices/{}/mark-completed", invoice_id),
+                "invoices_mark_completed",
+                self.route(
+                    Resource::Invoices,
+                    &format!("/{}/mark-completed", invoice_id),
+                ),
                 Method::PATCH,
             )
             .await;
@@ -446,7 +978,8 @@ impl SellAppClient {
     pub async fn invoices_mark_voided(&self, invoice_id: String) -> Result<Response, Error> {
         return self
             .send_request(
-                format!("v2/invoices/{}/mark-voided", invoice_id),
+                "invoices_mark_voided",
+                self.route(Resource::Invoices, &format!("/{}/mark-voided", invoice_id)),
                 Method::PATCH,
             )
             .await;
@@ -456,7 +989,8 @@ impl SellAppClient {
     ///
     /// ``invoice_id``: The ID of the invoice you want to issue a replacement for, required.
     ///
-    /// ``data``: JSON with the product variant ID's to get the replacements from.
+    /// ``data``: JSON with the product variant ID's to get the replacements from, see ``replacement::ReplacementRequest::to_data``.
+    /// Use ``invoices_replace`` to check the request against the invoice first.
     ///
     /// https://developer.sell.app/invoices-v2#issue-replacement-for-completed-invoice
     pub async fn invoices_issue_replacement(
@@ -466,7 +1000,11 @@ impl SellAppClient {
     ) -> Result<Response, Error> {
         return self
             .send_request_data(
-                format!("v2/invoices/{}/issue-replacement", invoice_id),
+                "invoices_issue_replacement",
+                self.route(
+                    Resource::Invoices,
+                    &format!("/{}/issue-replacement", invoice_id),
+                ),
                 Method::PATCH,
                 data,
             )
@@ -480,7 +1018,11 @@ impl SellAppClient {
     /// https://developer.sell.app/products-v2#list-all-products
     pub async fn products_list_all(&self, url_params: &str) -> Result<Response, Error> {
         return self
-            .send_request(format!("v2/products{}", url_params), Method::GET)
+            .send_request(
+                "products_list_all",
+                self.route(Resource::Products, url_params),
+                Method::GET,
+            )
             .await;
     }
 
@@ -491,7 +1033,12 @@ impl SellAppClient {
     /// https://developer.sell.app/products-v2#create-a-product
     pub async fn products_create(&self, data: String) -> Result<Response, Error> {
         return self
-            .send_request_data("v2/products".to_string(), Method::POST, data)
+            .send_request_data(
+                "products_create",
+                self.route(Resource::Products, ""),
+                Method::POST,
+                data,
+            )
             .await;
     }
 
@@ -502,7 +1049,11 @@ impl SellAppClient {
     /// https://developer.sell.app/products-v2#retrieve-a-product
     pub async fn products_get(&self, product_id: String) -> Result<Response, Error> {
         return self
-            .send_request(format!("v2/products/{}", product_id), Method::GET)
+            .send_request(
+                "products_get",
+                self.route(Resource::Products, &format!("/{}", product_id)),
+                Method::GET,
+            )
             .await;
     }
 
@@ -519,7 +1070,12 @@ impl SellAppClient {
         data: String,
     ) -> Result<Response, Error> {
         return self
-            .send_request_data(format!("v2/products/{}", product_id), Method::PATCH, data)
+            .send_request_data(
+                "products_update",
+                self.route(Resource::Products, &format!("/{}", product_id)),
+                Method::PATCH,
+                data,
+            )
             .await;
     }
 
@@ -530,7 +1086,11 @@ impl SellAppClient {
     /// https://developer.sell.app/products-v2#delete-a-product
     pub async fn products_delete(&self, product_id: String) -> Result<Response, Error> {
         return self
-            .send_request(format!("v2/products/{}", product_id), Method::DELETE)
+            .send_request(
+                "products_delete",
+                self.route(Resource::Products, &format!("/{}", product_id)),
+                Method::DELETE,
+            )
             .await;
     }
 
@@ -548,7 +1108,11 @@ impl SellAppClient {
     ) -> Result<Response, Error> {
         return self
             .send_request(
-                format!("v2/products/{}/variants{}", product_id, url_params),
+                "variants_list_all",
+                self.route(
+                    Resource::Products,
+                    &format!("/{}/variants{}", product_id, url_params),
+                ),
                 Method::GET,
             )
             .await;
@@ -568,7 +1132,8 @@ impl SellAppClient {
     ) -> Result<Response, Error> {
         return self
             .send_request_data(
-                format!("v2/products/{}/variants", product_id),
+                "variants_create",
+                self.route(Resource::Products, &format!("/{}/variants", product_id)),
                 Method::POST,
                 data,
             )
@@ -589,7 +1154,11 @@ impl SellAppClient {
     ) -> Result<Response, Error> {
         return self
             .send_request(
-                format!("v2/products/{}/variants/{}", product_id, variant_id),
+                "variants_get",
+                self.route(
+                    Resource::Products,
+                    &format!("/{}/variants/{}", product_id, variant_id),
+                ),
                 Method::GET,
             )
             .await;
@@ -612,7 +1181,11 @@ impl SellAppClient {
     ) -> Result<Response, Error> {
         return self
             .send_request_data(
-                format!("v2/products/{}/variants/{}", product_id, variant_id),
+                "variants_update",
+                self.route(
+                    Resource::Products,
+                    &format!("/{}/variants/{}", product_id, variant_id),
+                ),
                 Method::PATCH,
                 data,
             )
@@ -633,7 +1206,11 @@ impl SellAppClient {
     ) -> Result<Response, Error> {
         return self
             .send_request(
-                format!("v2/products/{}/variants/{}", product_id, variant_id),
+                "variants_delete",
+                self.route(
+                    Resource::Products,
+                    &format!("/{}/variants/{}", product_id, variant_id),
+                ),
                 Method::DELETE,
             )
             .await;
@@ -646,7 +1223,11 @@ impl SellAppClient {
     /// https://developer.sell.app/sections#list-all-sections
     pub async fn sections_list_all(&self, url_params: &str) -> Result<Response, Error> {
         return self
-            .send_request(format!("v1/sections{}", url_params), Method::GET)
+            .send_request(
+                "sections_list_all",
+                self.route(Resource::Sections, url_params),
+                Method::GET,
+            )
             .await;
     }
 
@@ -657,7 +1238,12 @@ impl SellAppClient {
     /// https://developer.sell.app/sections#create-a-section
     pub async fn sections_create(&self, data: String) -> Result<Response, Error> {
         return self
-            .send_request_data("v1/sections".to_string(), Method::POST, data)
+            .send_request_data(
+                "sections_create",
+                self.route(Resource::Sections, ""),
+                Method::POST,
+                data,
+            )
             .await;
     }
 
@@ -668,7 +1254,11 @@ impl SellAppClient {
     /// https://developer.sell.app/sections#retrieve-a-section
     pub async fn sections_get(&self, section_id: String) -> Result<Response, Error> {
         return self
-            .send_request(format!("v1/sections/{}", section_id), Method::GET)
+            .send_request(
+                "sections_get",
+                self.route(Resource::Sections, &format!("/{}", section_id)),
+                Method::GET,
+            )
             .await;
     }
 
@@ -685,7 +1275,12 @@ impl SellAppClient {
         data: String,
     ) -> Result<Response, Error> {
         return self
-            .send_request_data(format!("v1/sections/{}", section_id), Method::PATCH, data)
+            .send_request_data(
+                "sections_update",
+                self.route(Resource::Sections, &format!("/{}", section_id)),
+                Method::PATCH,
+                data,
+            )
             .await;
     }
 
@@ -696,7 +1291,11 @@ impl SellAppClient {
     /// https://developer.sell.app/sections#delete-a-section
     pub async fn sections_delete(&self, section_id: String) -> Result<Response, Error> {
         return self
-            .send_request(format!("v1/sections/{}", section_id), Method::DELETE)
+            .send_request(
+                "sections_delete",
+                self.route(Resource::Sections, &format!("/{}", section_id)),
+                Method::DELETE,
+            )
             .await;
     }
 
@@ -707,7 +1306,11 @@ impl SellAppClient {
     /// https://developer.sell.app/tickets#list-all-tickets
     pub async fn tickets_list_all(&self, url_params: &str) -> Result<Response, Error> {
         return self
-            .send_request(format!("v1/tickets{}", url_params), Method::GET)
+            .send_request(
+                "tickets_list_all",
+                self.route(Resource::Tickets, url_params),
+                Method::GET,
+            )
             .await;
     }
 
@@ -718,7 +1321,11 @@ impl SellAppClient {
     /// https://developer.sell.app/tickets#retrieve-specific-ticket
     pub async fn tickets_get(&self, ticket_id: String) -> Result<Response, Error> {
         return self
-            .send_request(format!("v1/tickets/{}", ticket_id), Method::GET)
+            .send_request(
+                "tickets_get",
+                self.route(Resource::Tickets, &format!("/{}", ticket_id)),
+                Method::GET,
+            )
             .await;
     }
 
@@ -736,7 +1343,11 @@ impl SellAppClient {
     ) -> Result<Response, Error> {
         return self
             .send_request(
-                format!("v1/tickets/{}/messages{}", ticket_id, url_params),
+                "tickets_list_messages",
+                self.route(
+                    Resource::Tickets,
+                    &format!("/{}/messages{}", ticket_id, url_params),
+                ),
                 Method::GET,
             )
             .await;
@@ -752,7 +1363,8 @@ impl SellAppClient {
     pub async fn tickets_reply(&self, ticket_id: String, data: String) -> Result<Response, Error> {
         return self
             .send_request_data(
-                format!("v1/tickets/{}/messages", ticket_id),
+                "tickets_reply",
+                self.route(Resource::Tickets, &format!("/{}/messages", ticket_id)),
                 Method::POST,
                 data,
             )
@@ -773,7 +1385,11 @@ impl SellAppClient {
     ) -> Result<Response, Error> {
         return self
             .send_request(
-                format!("v1/tickets/{}/messages/{}", ticket_id, msg_id),
+                "tickets_get_message",
+                self.route(
+                    Resource::Tickets,
+                    &format!("/{}/messages/{}", ticket_id, msg_id),
+                ),
                 Method::GET,
             )
             .await;
@@ -793,9 +1409,43 @@ pub fn init(api_key: &str, store_slug: &str) -> SellAppClient {
     let key = api_key.to_string();
     let slug = store_slug.to_string();
     let http_client = reqwest::Client::new();
-    return SellAppClient {
+    let client = SellAppClient {
         api_key: key,
         store_slug: slug,
+        transport: Arc::new(http_client.clone()),
         http_client,
+        middlewares: Vec::new(),
+        rate_limits: Vec::new(),
+        #[cfg(not(target_arch = "wasm32"))]
+        cache: None,
+        #[cfg(not(target_arch = "wasm32"))]
+        bypass_cache: false,
+        #[cfg(not(target_arch = "wasm32"))]
+        audit: None,
+        #[cfg(not(target_arch = "wasm32"))]
+        operator: None,
+        api_versions: HashMap::new(),
     };
+    #[cfg(feature = "metrics")]
+    let client = client.with_middleware(middleware::Metrics);
+    #[cfg(feature = "tracing")]
+    let client = client.with_middleware(middleware::Tracing);
+    return client;
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::transport::mock::{self, MockTransport};
+
+    #[tokio::test]
+    async fn replies_to_feedback() {
+        let transport = MockTransport::new(|_, _| mock::json(200, json!({ "data": {} })));
+        mock::client(&transport)
+            .feedback_reply("7".to_string(), json!({ "reply": "Thanks!" }).to_string())
+            .await
+            .unwrap();
+        assert_eq!(transport.requests(), ["PATCH v1/feedback/7"]);
+    }
 }
@@ -0,0 +1,425 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use reqwest::{header::RETRY_AFTER, Method, Request, Response, StatusCode, Url};
+
+use crate::{
+    rt::{self, Instant},
+    Error, SellAppClient,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use futures::future::BoxFuture;
+#[cfg(target_arch = "wasm32")]
+use futures::future::LocalBoxFuture as BoxFuture;
+
+#[cfg(feature = "metrics")]
+pub use crate::telemetry::Metrics;
+
+/// Future returned by ``Middleware::handle`` and ``Next::run``. It is ``Send`` on native targets only, like
+/// ``transport::TransportFuture``.
+pub type MiddlewareFuture<'a> = BoxFuture<'a, Result<Response, Error>>;
+
+/// Information about the API call a middleware is being invoked for.
+pub struct RequestContext {
+    /// Name of the client method that issued the call, e.g. **"invoices_create"**.
+    pub operation: &'static str,
+    /// HTTP method of the request.
+    pub method: Method,
+    /// Full URL of the request, including any URL parameters.
+    pub url: Url,
+    /// When the call was started, before any middleware ran.
+    pub started: Instant,
+}
+
+/// Marks responses that were answered locally instead of by the API, in their extensions.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LocalResponse {
+    /// A fresh entry of the response cache.
+    CacheHit,
+    /// A call blocked by ``dry_run::DryRun``.
+    DryRun,
+}
+
+/// The rest of the middleware stack, and finally the API, as seen by a middleware.
+///
+/// ``Next`` is ``Copy``, so a middleware can run it more than once, e.g. to retry a request.
+#[derive(Clone, Copy)]
+pub struct Next<'a> {
+    ctx: &'a RequestContext,
+    middlewares: &'a [Arc<dyn Middleware>],
+    client: &'a SellAppClient,
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(
+        ctx: &'a RequestContext,
+        middlewares: &'a [Arc<dyn Middleware>],
+        client: &'a SellAppClient,
+    ) -> Self {
+        return Next {
+            ctx,
+            middlewares,
+            client,
+        };
+    }
+
+    /// Pass ``request`` to the next middleware, or send it once every middleware ran.
+    pub fn run(self, request: Request) -> MiddlewareFuture<'a> {
+        return match self.middlewares.split_first() {
+            Some((middleware, rest)) => {
+                let next = Next {
+                    middlewares: rest,
+                    ..self
+                };
+                middleware.handle(self.ctx, request, next)
+            }
+            None => Box::pin(self.client.dispatch(self.ctx, request)),
+        };
+    }
+}
+
+/// Hooks that run around every request sent by a ``SellAppClient``.
+///
+/// Middlewares are added with ``SellAppClient::with_middleware``, and run in the order they were added: the first
+/// one added sees the request first and the response last. Simple middlewares implement the synchronous hooks,
+/// which all default to doing nothing. Middlewares that need to wait, or to send a request more than once, like
+/// ``Retry`` and ``RateLimit``, implement ``handle`` instead.
+///
+/// ```
+/// use sellapp::middleware::{Middleware, MiddlewareFuture, Next, RequestContext};
+///
+/// struct Staging;
+///
+/// impl Middleware for Staging {
+///     fn handle<'a>(
+///         &'a self,
+///         ctx: &'a RequestContext,
+///         mut request: reqwest::Request,
+///         next: Next<'a>,
+///     ) -> MiddlewareFuture<'a> {
+///         request.headers_mut().insert("X-Environment", "staging".parse().unwrap());
+///         return Box::pin(async move {
+///             let response = next.run(request).await?;
+///             println!("{} returned {}", ctx.operation, response.status());
+///             return Ok(response);
+///         });
+///     }
+/// }
+/// ```
+pub trait Middleware: Send + Sync {
+    /// Handle a request by passing it on to ``next``, or by answering it directly.
+    ///
+    /// The default implementation runs ``before_request``, ``after_response`` and ``on_error``.
+    fn handle<'a>(
+        &'a self,
+        ctx: &'a RequestContext,
+        mut request: Request,
+        next: Next<'a>,
+    ) -> MiddlewareFuture<'a> {
+        return Box::pin(async move {
+            if let Some(response) = self.before_request(ctx, &mut request) {
+                return Ok(response);
+            }
+            let res = next.run(request).await;
+            match &res {
+                Ok(response) => self.after_response(ctx, response),
+                Err(error) => self.on_error(ctx, error),
+            }
+            return res;
+        });
+    }
+
+    /// Called before the request is sent. The request can be modified, e.g. to add custom headers.
+    ///
+    /// Returning ``Some`` short-circuits the call: the request is not sent, later middlewares are skipped,
+    /// and the returned response is handed back to the caller instead.
+    fn before_request(&self, ctx: &RequestContext, request: &mut Request) -> Option<Response> {
+        let _ = (ctx, request);
+        return None;
+    }
+
+    /// Called after a response has been received, regardless of its status code.
+    fn after_response(&self, ctx: &RequestContext, response: &Response) {
+        let _ = (ctx, response);
+    }
+
+    /// Called if the request failed without receiving a response.
+    fn on_error(&self, ctx: &RequestContext, error: &Error) {
+        let _ = (ctx, error);
+    }
+}
+
+/// Middleware retrying failed calls with exponential back-off.
+///
+/// Calls answered with **429 Too Many Requests**, or that failed to connect, are retried for every HTTP method,
+/// as the API didn't process them. Calls answered with **500**, **502**, **503** or **504**, or that timed out,
+/// are only retried for GET, PUT and DELETE, unless ``with_retry_mutations`` is enabled, as a create may have
+/// gone through before the failure.
+///
+/// A ``Retry-After`` header in seconds is waited for, otherwise the delay doubles after every attempt.
+/// Add ``Retry`` before ``RateLimit``, so retries wait for the rate limiter as well.
+///
+/// ```
+/// use std::time::Duration;
+/// use sellapp::middleware::{RateLimit, Retry};
+///
+/// let sellapp_api = sellapp::init("your_api_key", "")
+///     .with_middleware(Retry::new(3))
+///     .with_middleware(RateLimit::new(60, Duration::from_secs(60)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Retry {
+    max_retries: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    retry_mutations: bool,
+}
+
+impl Retry {
+    /// Retry a call up to ``max_retries`` times, waiting 500ms before the first retry and at most 30s.
+    pub fn new(max_retries: u32) -> Self {
+        return Retry {
+            max_retries,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            retry_mutations: false,
+        };
+    }
+
+    /// Set the delay before the first retry, and the maximum delay the back-off grows to.
+    pub fn with_backoff(mut self, base_delay: Duration, max_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self.max_delay = max_delay;
+        return self;
+    }
+
+    /// Also retry POST and PATCH calls after server errors and timeouts, defaults to ``false``.
+    ///
+    /// Only enable this if duplicate creates are acceptable, or use ``SellAppClient::invoices_create_idempotent``.
+    pub fn with_retry_mutations(mut self, retry_mutations: bool) -> Self {
+        self.retry_mutations = retry_mutations;
+        return self;
+    }
+
+    /// How long to wait before retrying after ``res``, or ``None`` if it shouldn't be retried.
+    fn delay(
+        &self,
+        ctx: &RequestContext,
+        res: &Result<Response, Error>,
+        attempt: u32,
+    ) -> Option<Duration> {
+        let idempotent = self.retry_mutations
+            || matches!(
+                ctx.method,
+                Method::GET | Method::HEAD | Method::PUT | Method::DELETE
+            );
+        let retry_after = match res {
+            Ok(response) => match response.status() {
+                StatusCode::TOO_MANY_REQUESTS => retry_after(response),
+                StatusCode::INTERNAL_SERVER_ERROR
+                | StatusCode::BAD_GATEWAY
+                | StatusCode::SERVICE_UNAVAILABLE
+                | StatusCode::GATEWAY_TIMEOUT
+                    if idempotent =>
+                {
+                    retry_after(response)
+                }
+                _ => return None,
+            },
+            Err(error) if error.is_connect() => None,
+            Err(error) if error.is_timeout() && idempotent => None,
+            Err(_) => return None,
+        };
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        return Some(retry_after.unwrap_or(backoff));
+    }
+}
+
+impl Middleware for Retry {
+    fn handle<'a>(
+        &'a self,
+        ctx: &'a RequestContext,
+        mut request: Request,
+        next: Next<'a>,
+    ) -> MiddlewareFuture<'a> {
+        return Box::pin(async move {
+            let mut attempt = 0;
+            loop {
+                // Requests with a streaming body can't be cloned, and are only sent once.
+                let retry_request = match attempt < self.max_retries {
+                    true => request.try_clone(),
+                    false => None,
+                };
+                let res = next.run(request).await;
+                let (retry_request, delay) = match (retry_request, self.delay(ctx, &res, attempt)) {
+                    (Some(retry_request), Some(delay)) => (retry_request, delay),
+                    _ => return res,
+                };
+                drop(res);
+                #[cfg(feature = "metrics")]
+                crate::telemetry::record_retry(ctx);
+                rt::sleep(delay).await;
+                request = retry_request;
+                attempt += 1;
+            }
+        });
+    }
+}
+
+/// Middleware spacing out calls to stay under the API rate limit, with a token bucket.
+///
+/// Up to ``requests`` calls are sent at once, and the bucket refills evenly over ``per``. Calls wait for a free slot
+/// instead of failing. If the API still answers **429 Too Many Requests** with a ``Retry-After`` header, every call
+/// through the limiter waits that long.
+///
+/// Clones share the same bucket, so a single limiter can throttle several clients using the same API key.
+/// Clients created with ``SellAppClient::for_store`` share it too.
+#[derive(Clone)]
+pub struct RateLimit {
+    capacity: f64,
+    per_second: f64,
+    state: Arc<Mutex<RateLimitState>>,
+}
+
+struct RateLimitState {
+    tokens: f64,
+    refilled: Instant,
+    paused_until: Option<Instant>,
+}
+
+impl RateLimit {
+    /// Allow ``requests`` calls every ``per``, e.g. ``RateLimit::new(60, Duration::from_secs(60))``.
+    pub fn new(requests: u32, per: Duration) -> Self {
+        let capacity = requests.max(1) as f64;
+        return RateLimit {
+            capacity,
+            per_second: capacity / per.as_secs_f64().max(f64::EPSILON),
+            state: Arc::new(Mutex::new(RateLimitState {
+                tokens: capacity,
+                refilled: Instant::now(),
+                paused_until: None,
+            })),
+        };
+    }
+
+    /// Wait until a call may be sent, and take its slot. Returns how long it waited.
+    pub async fn acquire(&self) -> Duration {
+        let started = Instant::now();
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+                let now = Instant::now();
+                let elapsed = now.duration_since(state.refilled).as_secs_f64();
+                state.tokens = (state.tokens + elapsed * self.per_second).min(self.capacity);
+                state.refilled = now;
+                match state.paused_until {
+                    Some(until) if until > now => until - now,
+                    _ if state.tokens >= 1.0 => {
+                        state.tokens -= 1.0;
+                        return started.elapsed();
+                    }
+                    _ => Duration::from_secs_f64((1.0 - state.tokens) / self.per_second),
+                }
+            };
+            rt::sleep(wait).await;
+        }
+    }
+
+    /// Whether ``other`` is a clone of this limiter, sharing its bucket.
+    pub(crate) fn same_bucket(&self, other: &RateLimit) -> bool {
+        return Arc::ptr_eq(&self.state, &other.state);
+    }
+
+    /// Hold back every call until ``until``.
+    pub(crate) fn pause(&self, until: Instant) {
+        let mut state = self.state.lock().unwrap();
+        if state
+            .paused_until
+            .is_none_or(|paused_until| paused_until < until)
+        {
+            state.paused_until = Some(until);
+        }
+    }
+}
+
+impl Middleware for RateLimit {
+    fn handle<'a>(
+        &'a self,
+        ctx: &'a RequestContext,
+        request: Request,
+        next: Next<'a>,
+    ) -> MiddlewareFuture<'a> {
+        return Box::pin(async move {
+            let waited = self.acquire().await;
+            #[cfg(feature = "metrics")]
+            if !waited.is_zero() {
+                crate::telemetry::record_rate_limit_wait(ctx, waited);
+            }
+            #[cfg(not(feature = "metrics"))]
+            let _ = (ctx, waited);
+            let res = next.run(request).await;
+            if let Ok(response) = &res {
+                if response.status() == StatusCode::TOO_MANY_REQUESTS {
+                    if let Some(delay) = retry_after(response) {
+                        self.pause(Instant::now() + delay);
+                    }
+                }
+            }
+            return res;
+        });
+    }
+}
+
+/// Middleware recording every API call as a ``tracing`` span named **"sellapp_request"**.
+///
+/// The span has the ``operation`` (the client method name), ``method`` and ``path`` of the call, and the
+/// ``status`` of the response once received. Failed calls are logged as warnings.
+///
+/// Clients created with ``sellapp::init`` have this middleware installed by default when the ``tracing`` feature is enabled.
+#[cfg(feature = "tracing")]
+pub struct Tracing;
+
+#[cfg(feature = "tracing")]
+impl Middleware for Tracing {
+    fn handle<'a>(
+        &'a self,
+        ctx: &'a RequestContext,
+        request: Request,
+        next: Next<'a>,
+    ) -> MiddlewareFuture<'a> {
+        use tracing::Instrument;
+
+        let span = tracing::info_span!(
+            "sellapp_request",
+            operation = ctx.operation,
+            method = %ctx.method,
+            path = ctx.url.path(),
+            status = tracing::field::Empty,
+        );
+        let future = async move {
+            let res = next.run(request).await;
+            match &res {
+                Ok(response) => {
+                    tracing::Span::current().record("status", response.status().as_u16());
+                    tracing::debug!("received response");
+                }
+                Err(error) => tracing::warn!(%error, "request failed"),
+            }
+            return res;
+        };
+        return Box::pin(future.instrument(span));
+    }
+}
+
+/// Get the delay a ``Retry-After`` header in seconds asks for.
+pub(crate) fn retry_after(response: &Response) -> Option<Duration> {
+    let seconds = response.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    return Some(Duration::from_secs(seconds.trim().parse().ok()?));
+}
@@ -0,0 +1,332 @@
+use serde::{de, Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    money::{Currency, Money, MoneyError},
+    timestamp::Timestamp,
+};
+
+/// An invoice (order), as returned by the v2 invoice endpoints.
+///
+/// Missing attributes are left empty instead of failing deserialization, but prices that are present must be valid.
+/// Missing attributes are left empty instead of failing deserialization.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Invoice {
+    #[serde(deserialize_with = "string_or_number")]
+    pub id: String,
+    #[serde(deserialize_with = "nested_status")]
+    pub status: Option<String>,
+    pub payment: Payment,
+    pub customer_information: CustomerInformation,
+    #[serde(deserialize_with = "optional_string_or_number")]
+    pub coupon_id: Option<String>,
+    pub products: Vec<InvoiceProduct>,
+    #[serde(deserialize_with = "optional_timestamp")]
+    pub created_at: Option<Timestamp>,
+    #[serde(deserialize_with = "optional_timestamp")]
+    pub updated_at: Option<Timestamp>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Payment details of an invoice.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Payment {
+    pub gateway: Gateway,
+    /// Price before coupon discounts.
+    #[serde(deserialize_with = "optional_money")]
+    pub subtotal: Option<Money>,
+    /// Price paid by the customer.
+    #[serde(deserialize_with = "optional_money")]
+    pub total: Option<Money>,
+}
+
+/// The payment gateway an invoice was paid with.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Gateway {
+    /// Name of the gateway, e.g. **"STRIPE"** or **"PAYPAL"**.
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+}
+
+/// The customer an invoice belongs to.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CustomerInformation {
+    pub email: Option<String>,
+    /// ISO 3166-1 alpha-2 country code of the customer, e.g. **"US"**.
+    pub country: Option<String>,
+}
+
+/// A product included in an invoice.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InvoiceProduct {
+    #[serde(deserialize_with = "string_or_number")]
+    pub id: String,
+    pub title: Option<String>,
+    pub variants: Vec<InvoiceVariant>,
+}
+
+/// A product variant included in an invoice.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InvoiceVariant {
+    #[serde(deserialize_with = "string_or_number")]
+    pub id: String,
+    pub title: Option<String>,
+    pub quantity: u32,
+    /// Price of a single unit.
+    #[serde(deserialize_with = "optional_money")]
+    pub price: Option<Money>,
+}
+
+/// A product, as returned by the v2 product endpoints.
+///
+/// Prices are set per variant, see ``Variant``. Only the commonly used attributes are typed, everything else is kept
+/// in ``extra``.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Product {
+    #[serde(deserialize_with = "string_or_number")]
+    pub id: String,
+    pub title: Option<String>,
+    pub slug: Option<String>,
+    pub description: Option<String>,
+    /// E.g. **"PUBLIC"**, **"HIDDEN"** or **"PRIVATE"**.
+    pub visibility: Option<String>,
+    #[serde(deserialize_with = "optional_string_or_number")]
+    pub section_id: Option<String>,
+    /// The variants of the product, if the response includes them.
+    pub variants: Vec<Variant>,
+    #[serde(deserialize_with = "optional_timestamp")]
+    pub created_at: Option<Timestamp>,
+    #[serde(deserialize_with = "optional_timestamp")]
+    pub updated_at: Option<Timestamp>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A product variant, as returned by the v2 variant endpoints.
+///
+/// Only the commonly used attributes are typed, everything else (like ``deliverable``) is kept in ``extra``.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Variant {
+    #[serde(deserialize_with = "string_or_number")]
+    pub id: String,
+    #[serde(deserialize_with = "optional_string_or_number")]
+    pub product_id: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Price of a single unit.
+    #[serde(deserialize_with = "optional_money")]
+    pub price: Option<Money>,
+    #[serde(deserialize_with = "optional_timestamp")]
+    pub created_at: Option<Timestamp>,
+    #[serde(deserialize_with = "optional_timestamp")]
+    pub updated_at: Option<Timestamp>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A coupon, as returned by the coupon endpoints.
+///
+/// Only the commonly used attributes are typed, everything else is kept in ``extra``.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Coupon {
+    #[serde(deserialize_with = "string_or_number")]
+    pub id: String,
+    pub code: Option<String>,
+    /// **"PERCENTAGE"** or **"FIXED"**.
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    /// The discount as sent by the API: a percentage, or an amount in major units for fixed coupons.
+    #[serde(deserialize_with = "optional_string_or_number")]
+    pub discount: Option<String>,
+    #[serde(deserialize_with = "optional_timestamp")]
+    pub created_at: Option<Timestamp>,
+    #[serde(deserialize_with = "optional_timestamp")]
+    pub updated_at: Option<Timestamp>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Coupon {
+    /// The discount of a **"FIXED"** coupon in ``currency``, usually the currency of the store.
+    ///
+    /// Returns ``Ok(None)`` for other coupon types, or if the coupon has no discount.
+    pub fn fixed_discount(&self, currency: Currency) -> Result<Option<Money>, MoneyError> {
+        if !self
+            .kind
+            .as_deref()
+            .is_some_and(|kind| kind.eq_ignore_ascii_case("FIXED"))
+        {
+            return Ok(None);
+        }
+        return match &self.discount {
+            Some(discount) => Ok(Some(Money::from_major_str(discount, currency)?)),
+            None => Ok(None),
+        };
+    }
+}
+
+/// A support ticket, as returned by the ticket endpoints.
+///
+/// Only the commonly used attributes are typed, everything else is kept in ``extra``.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Ticket {
+    #[serde(deserialize_with = "string_or_number")]
+    pub id: String,
+    pub subject: Option<String>,
+    pub email: Option<String>,
+    #[serde(deserialize_with = "optional_timestamp")]
+    pub created_at: Option<Timestamp>,
+    #[serde(deserialize_with = "optional_timestamp")]
+    pub updated_at: Option<Timestamp>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Customer feedback on an invoice, as returned by the feedback endpoints.
+///
+/// Only the commonly used attributes are typed, everything else is kept in ``extra``.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Feedback {
+    #[serde(deserialize_with = "string_or_number")]
+    pub id: String,
+    #[serde(deserialize_with = "optional_string_or_number")]
+    pub invoice_id: Option<String>,
+    pub message: Option<String>,
+    pub reply: Option<String>,
+    #[serde(deserialize_with = "optional_timestamp")]
+    pub created_at: Option<Timestamp>,
+    #[serde(deserialize_with = "optional_timestamp")]
+    pub updated_at: Option<Timestamp>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Accept IDs and amounts sent either as JSON numbers or strings.
+fn string_or_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    return Ok(optional_string_or_number(deserializer)?.unwrap_or_default());
+}
+
+fn optional_string_or_number<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    return Ok(match Value::deserialize(deserializer)? {
+        Value::String(value) => Some(value),
+        Value::Number(value) => Some(value.to_string()),
+        _ => None,
+    });
+}
+
+/// Accept any representation of ``Money``, or ``null``.
+///
+/// Amounts without a valid currency or amount fail, rather than being read as missing.
+fn optional_money<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Money>, D::Error> {
+    return match Value::deserialize(deserializer)? {
+        Value::Null => Ok(None),
+        value => Money::from_value(&value)
+            .map(Some)
+            .map_err(|error| de::Error::custom(format!("invalid price {}: {}", value, error))),
+    };
+}
+
+/// Accept any timestamp ``Timestamp`` can parse, leaving invalid ones empty.
+fn optional_timestamp<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Timestamp>, D::Error> {
+    let value = Value::deserialize(deserializer)?;
+    return Ok(value
+        .as_str()
+        .and_then(|value| Timestamp::parse(value).ok()));
+}
+
+/// Accept both a plain status string and the nested ``status.status.status`` object of v2 invoices.
+fn nested_status<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    let mut status = Value::deserialize(deserializer)?;
+    while let Some(nested) = status.get_mut("status") {
+        status = nested.take();
+    }
+    return Ok(status.as_str().map(|status| status.to_uppercase()));
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn usd() -> Currency {
+        return Currency::new("USD").unwrap();
+    }
+
+    #[test]
+    fn parses_variant_prices() {
+        let variant: Variant = serde_json::from_value(json!({
+            "id": 12,
+            "product_id": "3",
+            "price": { "price": "4.99", "currency": "USD" },
+        }))
+        .unwrap();
+        assert_eq!(variant.id, "12");
+        assert_eq!(variant.product_id.as_deref(), Some("3"));
+        assert_eq!(variant.price, Some(Money::new(499, usd())));
+
+        let variant: Variant =
+            serde_json::from_value(json!({ "price": { "base": 1000, "currency": "JPY" } }))
+                .unwrap();
+        assert_eq!(variant.price.unwrap().to_string(), "1000 JPY");
+
+        let variant: Variant = serde_json::from_value(json!({ "price": null })).unwrap();
+        assert_eq!(variant.price, None);
+
+        let error = serde_json::from_value::<Variant>(json!({ "price": "4.99" })).unwrap_err();
+        assert!(
+            error.to_string().starts_with("invalid price \"4.99\""),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn parses_products_with_variants() {
+        let product: Product = serde_json::from_value(json!({
+            "id": 3,
+            "slug": "steam-key",
+            "variants": [{ "id": 12, "price": { "base": "499", "currency": "EUR" } }],
+            "custom": true,
+        }))
+        .unwrap();
+        assert_eq!(product.slug.as_deref(), Some("steam-key"));
+        assert_eq!(product.variants[0].price.unwrap().to_string(), "4.99 EUR");
+        assert_eq!(product.extra["custom"], json!(true));
+    }
+
+    #[test]
+    fn reads_fixed_coupon_discounts() {
+        let fixed: Coupon =
+            serde_json::from_value(json!({ "id": 1, "type": "FIXED", "discount": "2.50" }))
+                .unwrap();
+        assert_eq!(
+            fixed.fixed_discount(usd()),
+            Ok(Some(Money::new(250, usd())))
+        );
+
+        let percentage: Coupon =
+            serde_json::from_value(json!({ "type": "PERCENTAGE", "discount": 10 })).unwrap();
+        assert_eq!(percentage.fixed_discount(usd()), Ok(None));
+
+        let invalid: Coupon =
+            serde_json::from_value(json!({ "type": "FIXED", "discount": "abc" })).unwrap();
+        assert!(invalid.fixed_discount(usd()).is_err());
+    }
+}
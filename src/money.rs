@@ -0,0 +1,489 @@
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+/// Currencies whose minor unit is not a hundredth of the major unit, with their amount of decimal places.
+///
+/// From the ISO 4217 list, every other currency has two decimal places.
+const MINOR_UNIT_EXCEPTIONS: [(&str, u32); 26] = [
+    ("BHD", 3),
+    ("BIF", 0),
+    ("CLF", 4),
+    ("CLP", 0),
+    ("DJF", 0),
+    ("GNF", 0),
+    ("IQD", 3),
+    ("ISK", 0),
+    ("JOD", 3),
+    ("JPY", 0),
+    ("KMF", 0),
+    ("KRW", 0),
+    ("KWD", 3),
+    ("LYD", 3),
+    ("OMR", 3),
+    ("PYG", 0),
+    ("RWF", 0),
+    ("TND", 3),
+    ("UGX", 0),
+    ("UYI", 0),
+    ("UYW", 4),
+    ("VND", 0),
+    ("VUV", 0),
+    ("XAF", 0),
+    ("XOF", 0),
+    ("XPF", 0),
+];
+
+/// An ISO 4217 currency code, e.g. **"USD"**.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Currency([u8; 3]);
+
+impl Currency {
+    /// Get the currency with the given code, or ``None`` if it isn't three ASCII letters. The code is uppercased.
+    pub fn new(code: &str) -> Option<Self> {
+        let bytes = code.trim().as_bytes();
+        if bytes.len() != 3 || !bytes.iter().all(u8::is_ascii_alphabetic) {
+            return None;
+        }
+        return Some(Currency([
+            bytes[0].to_ascii_uppercase(),
+            bytes[1].to_ascii_uppercase(),
+            bytes[2].to_ascii_uppercase(),
+        ]));
+    }
+
+    pub fn code(&self) -> &str {
+        return std::str::from_utf8(&self.0).unwrap();
+    }
+
+    /// Amount of decimal places of the currency, e.g. 2 for **"USD"** and 0 for **"JPY"**.
+    pub fn minor_units(&self) -> u32 {
+        return MINOR_UNIT_EXCEPTIONS
+            .iter()
+            .find(|(code, _)| *code == self.code())
+            .map_or(2, |(_, units)| *units);
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f.write_str(self.code());
+    }
+}
+
+impl fmt::Debug for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "Currency({})", self.code());
+    }
+}
+
+impl FromStr for Currency {
+    type Err = MoneyError;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        return Currency::new(code).ok_or_else(|| MoneyError::InvalidCurrency(code.to_string()));
+    }
+}
+
+impl Serialize for Currency {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        return serializer.serialize_str(self.code());
+    }
+}
+
+impl<'de> Deserialize<'de> for Currency {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        return code.parse().map_err(de::Error::custom);
+    }
+}
+
+/// Error returned by ``Money`` arithmetic and parsing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoneyError {
+    /// The amounts are in different currencies.
+    CurrencyMismatch(Currency, Currency),
+    /// The result doesn't fit in an ``i64`` amount of minor units, or a division by zero.
+    Overflow,
+    /// The currency code is not three letters.
+    InvalidCurrency(String),
+    /// The amount is not a decimal number.
+    InvalidAmount(String),
+}
+
+impl fmt::Display for MoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            MoneyError::CurrencyMismatch(a, b) => write!(f, "currency mismatch: {} and {}", a, b),
+            MoneyError::Overflow => write!(f, "amount out of range"),
+            MoneyError::InvalidCurrency(code) => write!(f, "invalid currency code: {:?}", code),
+            MoneyError::InvalidAmount(amount) => write!(f, "invalid amount: {:?}", amount),
+        };
+    }
+}
+
+impl std::error::Error for MoneyError {}
+
+/// An exact amount of money, counted in the minor unit of its currency (e.g. cents).
+///
+/// Arithmetic is checked: adding amounts in different currencies or overflowing returns an error instead of
+/// a wrong total. Amounts are only compared when they share a currency.
+///
+/// Deserializes from the representations used by the API: ``{"base": 1999, "currency": "USD"}`` with the amount in
+/// minor units as a number or string, or ``{"price": "19.99", "currency": "USD"}`` (also ``"amount"``) with a decimal
+/// amount in major units, which is rounded to the minor units of the currency. Serializes to the first form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Money {
+    pub amount_minor: i64,
+    pub currency: Currency,
+}
+
+impl Money {
+    pub fn new(amount_minor: i64, currency: Currency) -> Self {
+        return Money {
+            amount_minor,
+            currency,
+        };
+    }
+
+    pub fn zero(currency: Currency) -> Self {
+        return Money::new(0, currency);
+    }
+
+    /// Parse a decimal amount in major units, e.g. **"19.99"**, rounding half away from zero to the minor units
+    /// of ``currency``.
+    pub fn from_major_str(amount: &str, currency: Currency) -> Result<Self, MoneyError> {
+        let invalid = || MoneyError::InvalidAmount(amount.to_string());
+        let trimmed = amount.trim();
+        let (negative, digits) = match trimmed.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+        };
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        let all_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if (whole.is_empty() && fraction.is_empty()) || !all_digits(whole) || !all_digits(fraction)
+        {
+            return Err(invalid());
+        }
+
+        let units = currency.minor_units() as usize;
+        let mut minor: i64 = 0;
+        for digit in whole
+            .bytes()
+            .chain(fraction.bytes().chain(std::iter::repeat(b'0')).take(units))
+        {
+            minor = minor
+                .checked_mul(10)
+                .and_then(|minor| minor.checked_add((digit - b'0') as i64))
+                .ok_or(MoneyError::Overflow)?;
+        }
+        if fraction
+            .as_bytes()
+            .get(units)
+            .is_some_and(|digit| *digit >= b'5')
+        {
+            minor = minor.checked_add(1).ok_or(MoneyError::Overflow)?;
+        }
+        if negative {
+            minor = -minor;
+        }
+        return Ok(Money::new(minor, currency));
+    }
+
+    /// Format the amount in major units with the decimal places of the currency, e.g. **"19.90"**.
+    pub fn to_major_string(&self) -> String {
+        let units = self.currency.minor_units();
+        let sign = if self.amount_minor < 0 { "-" } else { "" };
+        let minor = self.amount_minor.unsigned_abs();
+        if units == 0 {
+            return format!("{}{}", sign, minor);
+        }
+        let scale = 10u64.pow(units);
+        return format!(
+            "{}{}.{:0width$}",
+            sign,
+            minor / scale,
+            minor % scale,
+            width = units as usize
+        );
+    }
+
+    pub fn is_zero(&self) -> bool {
+        return self.amount_minor == 0;
+    }
+
+    pub fn checked_add(self, other: Money) -> Result<Money, MoneyError> {
+        self.same_currency(&other)?;
+        let amount = self.amount_minor.checked_add(other.amount_minor);
+        return amount
+            .map(|amount| Money::new(amount, self.currency))
+            .ok_or(MoneyError::Overflow);
+    }
+
+    pub fn checked_sub(self, other: Money) -> Result<Money, MoneyError> {
+        self.same_currency(&other)?;
+        let amount = self.amount_minor.checked_sub(other.amount_minor);
+        return amount
+            .map(|amount| Money::new(amount, self.currency))
+            .ok_or(MoneyError::Overflow);
+    }
+
+    /// Multiply by a whole number, e.g. a quantity.
+    pub fn checked_mul(self, factor: i64) -> Result<Money, MoneyError> {
+        let amount = self.amount_minor.checked_mul(factor);
+        return amount
+            .map(|amount| Money::new(amount, self.currency))
+            .ok_or(MoneyError::Overflow);
+    }
+
+    /// Multiply by ``numerator / denominator``, rounding half away from zero to a whole minor unit.
+    ///
+    /// Useful to apply a discount ratio, e.g. scaling a line price by an invoice's total divided by its subtotal.
+    pub fn checked_scale(self, numerator: i64, denominator: i64) -> Result<Money, MoneyError> {
+        if denominator == 0 {
+            return Err(MoneyError::Overflow);
+        }
+        let product = self.amount_minor as i128 * numerator as i128;
+        let denominator = denominator as i128;
+        let quotient = product / denominator;
+        let remainder = product % denominator;
+        let rounded = if remainder.abs() * 2 >= denominator.abs() {
+            quotient
+                + if (product < 0) != (denominator < 0) {
+                    -1
+                } else {
+                    1
+                }
+        } else {
+            quotient
+        };
+        let amount = i64::try_from(rounded).map_err(|_| MoneyError::Overflow)?;
+        return Ok(Money::new(amount, self.currency));
+    }
+
+    /// Read any of the representations described on ``Money`` from a JSON value.
+    pub(crate) fn from_value(value: &Value) -> Result<Money, MoneyError> {
+        let currency_code = value["currency"].as_str().unwrap_or_default();
+        let currency: Currency = currency_code.parse()?;
+        if let Some(base) = number_string(&value["base"]) {
+            // Some responses send minor units as "1999.0".
+            let whole = base.strip_suffix(".0").unwrap_or(&base);
+            let amount = whole
+                .parse()
+                .map_err(|_| MoneyError::InvalidAmount(base.clone()))?;
+            return Ok(Money::new(amount, currency));
+        }
+        for field in ["price", "amount"] {
+            if let Some(amount) = number_string(&value[field]) {
+                return Money::from_major_str(&amount, currency);
+            }
+        }
+        return Err(MoneyError::InvalidAmount(value.to_string()));
+    }
+
+    fn same_currency(&self, other: &Money) -> Result<(), MoneyError> {
+        if self.currency != other.currency {
+            return Err(MoneyError::CurrencyMismatch(self.currency, other.currency));
+        }
+        return Ok(());
+    }
+}
+
+impl PartialOrd for Money {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self.currency != other.currency {
+            return None;
+        }
+        return Some(self.amount_minor.cmp(&other.amount_minor));
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{} {}", self.to_major_string(), self.currency);
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Repr<'a> {
+            base: String,
+            currency: &'a str,
+        }
+        let repr = Repr {
+            base: self.amount_minor.to_string(),
+            currency: self.currency.code(),
+        };
+        return repr.serialize(serializer);
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        return Money::from_value(&Value::deserialize(deserializer)?).map_err(de::Error::custom);
+    }
+}
+
+fn number_string(value: &Value) -> Option<String> {
+    return match value {
+        Value::String(value) => Some(value.clone()),
+        Value::Number(value) => Some(value.to_string()),
+        _ => None,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn currency(code: &str) -> Currency {
+        return Currency::new(code).unwrap();
+    }
+
+    fn major(amount: &str, code: &str) -> Result<i64, MoneyError> {
+        return Money::from_major_str(amount, currency(code)).map(|money| money.amount_minor);
+    }
+
+    #[test]
+    fn parses_major_amounts() {
+        assert_eq!(major("19.99", "USD"), Ok(1999));
+        assert_eq!(major("19.9", "USD"), Ok(1990));
+        assert_eq!(major("19", "USD"), Ok(1900));
+        assert_eq!(major(".5", "USD"), Ok(50));
+        assert_eq!(major("5.", "USD"), Ok(500));
+        assert_eq!(major(" +1.00 ", "USD"), Ok(100));
+        assert_eq!(major("0", "USD"), Ok(0));
+    }
+
+    #[test]
+    fn rounds_major_amounts_half_away_from_zero() {
+        assert_eq!(major("1.005", "USD"), Ok(101));
+        assert_eq!(major("1.0049", "USD"), Ok(100));
+        assert_eq!(major("-1.005", "USD"), Ok(-101));
+        assert_eq!(major("-1.0049", "USD"), Ok(-100));
+        assert_eq!(major("0.999", "USD"), Ok(100));
+    }
+
+    #[test]
+    fn parses_negative_amounts() {
+        assert_eq!(major("-19.99", "USD"), Ok(-1999));
+        assert_eq!(
+            Money::new(-1999, currency("USD")).to_major_string(),
+            "-19.99"
+        );
+        assert_eq!(Money::new(-5, currency("USD")).to_major_string(), "-0.05");
+    }
+
+    #[test]
+    fn uses_the_minor_units_of_the_currency() {
+        assert_eq!(major("1000", "JPY"), Ok(1000));
+        assert_eq!(major("999.5", "JPY"), Ok(1000));
+        assert_eq!(major("1.2345", "KWD"), Ok(1235));
+        assert_eq!(major("1.5", "BHD"), Ok(1500));
+        assert_eq!(Money::new(1000, currency("JPY")).to_major_string(), "1000");
+        assert_eq!(Money::new(1500, currency("BHD")).to_major_string(), "1.500");
+        assert_eq!(Money::new(7, currency("KWD")).to_major_string(), "0.007");
+    }
+
+    #[test]
+    fn rejects_invalid_amounts() {
+        for amount in ["", "-", ".", "1,00", "1.2.3", "abc", "--1", "1e3", "- 1"] {
+            assert_eq!(
+                major(amount, "USD"),
+                Err(MoneyError::InvalidAmount(amount.to_string())),
+                "{:?}",
+                amount
+            );
+        }
+    }
+
+    #[test]
+    fn detects_overflow() {
+        assert_eq!(major("92233720368547758.07", "USD"), Ok(i64::MAX));
+        assert_eq!(
+            major("92233720368547758.08", "USD"),
+            Err(MoneyError::Overflow)
+        );
+        assert_eq!(
+            major("92233720368547758.075", "USD"),
+            Err(MoneyError::Overflow)
+        );
+        let max = Money::new(i64::MAX, currency("USD"));
+        let cent = Money::new(1, currency("USD"));
+        assert_eq!(max.checked_add(cent), Err(MoneyError::Overflow));
+        assert_eq!(max.checked_mul(2), Err(MoneyError::Overflow));
+        assert_eq!(max.checked_scale(3, 2), Err(MoneyError::Overflow));
+        assert_eq!(cent.checked_scale(1, 0), Err(MoneyError::Overflow));
+        assert_eq!(max.checked_scale(2, 2), Ok(max));
+    }
+
+    #[test]
+    fn scales_rounding_half_away_from_zero() {
+        let usd = currency("USD");
+        let scale = |amount, numerator, denominator| {
+            return Money::new(amount, usd)
+                .checked_scale(numerator, denominator)
+                .unwrap()
+                .amount_minor;
+        };
+        assert_eq!(scale(1000, 9, 10), 900);
+        assert_eq!(scale(5, 1, 2), 3);
+        assert_eq!(scale(-5, 1, 2), -3);
+        assert_eq!(scale(5, -1, 2), -3);
+        assert_eq!(scale(5, 1, -2), -3);
+        assert_eq!(scale(-5, -1, -2), -3);
+        assert_eq!(scale(10, 1, 3), 3);
+        assert_eq!(scale(20, 1, 3), 7);
+        assert_eq!(scale(-20, 1, 3), -7);
+    }
+
+    #[test]
+    fn checks_currencies() {
+        let usd = Money::new(100, currency("USD"));
+        let eur = Money::new(100, currency("EUR"));
+        assert_eq!(
+            usd.checked_add(eur),
+            Err(MoneyError::CurrencyMismatch(usd.currency, eur.currency))
+        );
+        assert_eq!(usd.partial_cmp(&eur), None);
+        assert!(usd < Money::new(101, currency("usd")));
+    }
+
+    #[test]
+    fn reads_the_minor_unit_table() {
+        assert_eq!(currency("USD").minor_units(), 2);
+        assert_eq!(currency("eur").minor_units(), 2);
+        assert_eq!(currency("JPY").minor_units(), 0);
+        assert_eq!(currency("KRW").minor_units(), 0);
+        assert_eq!(currency("KWD").minor_units(), 3);
+        assert_eq!(currency("CLF").minor_units(), 4);
+        assert!(MINOR_UNIT_EXCEPTIONS
+            .windows(2)
+            .all(|pair| pair[0].0 < pair[1].0));
+        assert!(Currency::new("US").is_none());
+        assert!(Currency::new("U5D").is_none());
+    }
+
+    #[test]
+    fn reads_api_representations() {
+        let usd = currency("USD");
+        let value = serde_json::json!({ "base": 1999, "currency": "USD" });
+        assert_eq!(Money::from_value(&value), Ok(Money::new(1999, usd)));
+        let value = serde_json::json!({ "base": "1999.0", "currency": "usd" });
+        assert_eq!(Money::from_value(&value), Ok(Money::new(1999, usd)));
+        let value = serde_json::json!({ "amount": 19.99, "currency": "USD" });
+        assert_eq!(Money::from_value(&value), Ok(Money::new(1999, usd)));
+        let value = serde_json::json!({ "price": "19.99" });
+        assert!(matches!(
+            Money::from_value(&value),
+            Err(MoneyError::InvalidCurrency(_))
+        ));
+        let money: Money = serde_json::from_str(r#"{"base":"-250","currency":"EUR"}"#).unwrap();
+        assert_eq!(
+            serde_json::to_string(&money).unwrap(),
+            r#"{"base":"-250","currency":"EUR"}"#
+        );
+    }
+}
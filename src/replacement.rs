@@ -0,0 +1,237 @@
+use std::{collections::BTreeMap, fmt};
+
+use serde_json::{json, Map, Value};
+
+use crate::{
+    deliverable::Deliverable, models::Invoice, stock::serials, util::unwrap_data, SellAppClient,
+};
+
+/// A single variant of an invoice to be replaced.
+#[derive(Debug, Clone)]
+pub struct ReplacementLine {
+    pub variant_id: String,
+    /// How many units to replace, at most the amount bought.
+    pub quantity: u32,
+}
+
+/// The variants of an invoice to issue a replacement for, see ``SellAppClient::invoices_replace``.
+#[derive(Debug, Clone, Default)]
+pub struct ReplacementRequest {
+    pub lines: Vec<ReplacementLine>,
+}
+
+impl ReplacementRequest {
+    pub fn new() -> Self {
+        return ReplacementRequest::default();
+    }
+
+    /// Replace ``quantity`` units of a variant.
+    pub fn with_variant(mut self, variant_id: impl Into<String>, quantity: u32) -> Self {
+        self.lines.push(ReplacementLine {
+            variant_id: variant_id.into(),
+            quantity,
+        });
+        return self;
+    }
+
+    /// Requested quantity of each variant, adding up lines of the same variant.
+    pub fn quantities(&self) -> BTreeMap<String, u32> {
+        let mut quantities = BTreeMap::new();
+        for line in &self.lines {
+            let quantity = quantities.entry(line.variant_id.clone()).or_insert(0u32);
+            *quantity = quantity.saturating_add(line.quantity);
+        }
+        return quantities;
+    }
+
+    /// JSON body for ``SellAppClient::invoices_issue_replacement``, e.g. **{"replacements": {"12": 1}}**.
+    pub fn to_data(&self) -> Value {
+        let replacements: Map<String, Value> = self
+            .quantities()
+            .into_iter()
+            .map(|(variant_id, quantity)| (variant_id, json!(quantity)))
+            .collect();
+        return json!({ "replacements": replacements });
+    }
+}
+
+/// Error returned while issuing a replacement with ``SellAppClient::invoices_replace``.
+///
+/// Every error except ``Http`` is found before the replacement is issued, so nothing was changed. ``Http`` errors
+/// can also come from fetching the deliverables once the replacement was issued.
+#[derive(Debug)]
+pub enum ReplacementError {
+    /// Calling the API failed.
+    Http(crate::Error),
+    /// The invoice in the response couldn't be parsed.
+    Parse(serde_json::Error),
+    /// The request doesn't name any variant.
+    Empty,
+    /// Replacements can only be issued for completed invoices.
+    NotCompleted { status: Option<String> },
+    /// A variant isn't part of the invoice.
+    UnknownVariant(String),
+    /// More units of a variant were requested than bought, or none at all.
+    InvalidQuantity {
+        variant_id: String,
+        requested: u32,
+        bought: u32,
+    },
+    /// A variant doesn't have enough serial keys left to replace the requested units.
+    OutOfStock {
+        variant_id: String,
+        requested: u32,
+        available: usize,
+    },
+}
+
+impl fmt::Display for ReplacementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            ReplacementError::Http(error) => write!(f, "request failed: {}", error),
+            ReplacementError::Parse(error) => write!(f, "failed to parse invoice: {}", error),
+            ReplacementError::Empty => write!(f, "no variants to replace"),
+            ReplacementError::NotCompleted { status } => write!(
+                f,
+                "invoice is not completed (status {})",
+                status.as_deref().unwrap_or("unknown")
+            ),
+            ReplacementError::UnknownVariant(variant_id) => {
+                write!(f, "variant {} is not part of the invoice", variant_id)
+            }
+            ReplacementError::InvalidQuantity {
+                variant_id,
+                requested,
+                bought,
+            } => write!(
+                f,
+                "can't replace {} units of variant {}, {} were bought",
+                requested, variant_id, bought
+            ),
+            ReplacementError::OutOfStock {
+                variant_id,
+                requested,
+                available,
+            } => write!(
+                f,
+                "variant {} has {} serial keys left, {} are needed",
+                variant_id, available, requested
+            ),
+        };
+    }
+}
+
+impl std::error::Error for ReplacementError {}
+
+impl From<crate::Error> for ReplacementError {
+    fn from(error: crate::Error) -> Self {
+        return ReplacementError::Http(error);
+    }
+}
+
+impl From<reqwest::Error> for ReplacementError {
+    fn from(error: reqwest::Error) -> Self {
+        return ReplacementError::Http(error.into());
+    }
+}
+
+impl From<serde_json::Error> for ReplacementError {
+    fn from(error: serde_json::Error) -> Self {
+        return ReplacementError::Parse(error);
+    }
+}
+
+impl SellAppClient {
+    /// Issue a replacement for an invoice after checking it can be fulfilled.
+    ///
+    /// Checks that the invoice is completed, that every requested variant was bought in it at least as many times
+    /// as requested, and that variants delivering serials have enough keys left. Only then the replacement is
+    /// issued with ``invoices_issue_replacement``.
+    ///
+    /// Returns the deliverables of the invoice once the replacement was issued, see ``invoices_get_deliverables``.
+    ///
+    /// ``invoice_id``: The ID of the invoice you want to issue a replacement for, required.
+    ///
+    /// ``request``: The variants and quantities to replace, required.
+    ///
+    /// ```no_run
+    /// use sellapp::replacement::ReplacementRequest;
+    ///
+    /// # async fn run() -> Result<(), sellapp::replacement::ReplacementError> {
+    /// let sellapp_api = sellapp::init("your_api_key", "");
+    /// let request = ReplacementRequest::new().with_variant("456", 1);
+    /// let deliverables = sellapp_api.invoices_replace("123".to_string(), &request).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn invoices_replace(
+        &self,
+        invoice_id: String,
+        request: &ReplacementRequest,
+    ) -> Result<Vec<Deliverable>, ReplacementError> {
+        let quantities = request.quantities();
+        if quantities.is_empty() {
+            return Err(ReplacementError::Empty);
+        }
+
+        let client = self.uncached();
+        let res = client
+            .invoices_get(invoice_id.clone())
+            .await?
+            .error_for_status()?;
+        let invoice: Invoice = serde_json::from_value(unwrap_data(res.json::<Value>().await?))?;
+        if invoice.status.as_deref() != Some("COMPLETED") {
+            return Err(ReplacementError::NotCompleted {
+                status: invoice.status,
+            });
+        }
+
+        let mut lines = Vec::new();
+        for (variant_id, requested) in quantities {
+            let mut product_id = None;
+            let mut bought = 0;
+            for product in &invoice.products {
+                for variant in &product.variants {
+                    if variant.id == variant_id {
+                        product_id = Some(product.id.clone());
+                        bought += variant.quantity;
+                    }
+                }
+            }
+            let product_id = match product_id {
+                Some(product_id) => product_id,
+                None => return Err(ReplacementError::UnknownVariant(variant_id)),
+            };
+            if requested == 0 || requested > bought {
+                return Err(ReplacementError::InvalidQuantity {
+                    variant_id,
+                    requested,
+                    bought,
+                });
+            }
+            lines.push((product_id, variant_id, requested));
+        }
+
+        for (product_id, variant_id, requested) in lines {
+            let res = client
+                .variants_get(product_id, variant_id.clone())
+                .await?
+                .error_for_status()?;
+            let variant = unwrap_data(res.json::<Value>().await?);
+            if let Some(serials) = serials(&variant) {
+                if serials.len() < requested as usize {
+                    return Err(ReplacementError::OutOfStock {
+                        variant_id,
+                        requested,
+                        available: serials.len(),
+                    });
+                }
+            }
+        }
+
+        self.invoices_issue_replacement(invoice_id.clone(), request.to_data().to_string())
+            .await?
+            .error_for_status()?;
+        return Ok(client.invoices_get_deliverables(invoice_id).await?);
+    }
+}
@@ -0,0 +1,259 @@
+use std::fmt;
+
+use reqwest::{Response, Url};
+
+use crate::Error;
+use serde_json::Value;
+
+use crate::{timestamp::Timestamp, SellAppClient};
+
+/// Amount of items requested per page by the paging helpers.
+pub const PAGE_SIZE: usize = 100;
+
+/// URL parameters that sort list endpoints by update time, newest first.
+pub const SORT_BY_UPDATED_DESC: &str = "&sort=updated_at&order=desc";
+
+/// URL parameters that sort list endpoints by creation time, newest first.
+pub const SORT_BY_CREATED_DESC: &str = "&sort=created_at&order=desc";
+
+/// A top-level resource type of the API that can be listed.
+///
+/// Product variants are not included, as they are listed per product with ``SellAppClient::variants_list_all``.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Resource {
+    Blacklists,
+    Coupons,
+    Feedback,
+    Groups,
+    Invoices,
+    Products,
+    Sections,
+    Tickets,
+}
+
+impl Resource {
+    pub const ALL: [Resource; 8] = [
+        Resource::Blacklists,
+        Resource::Coupons,
+        Resource::Feedback,
+        Resource::Groups,
+        Resource::Invoices,
+        Resource::Products,
+        Resource::Sections,
+        Resource::Tickets,
+    ];
+
+    /// Name of the resource as it appears in the API path, e.g. **"products"**.
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Resource::Blacklists => "blacklists",
+            Resource::Coupons => "coupons",
+            Resource::Feedback => "feedback",
+            Resource::Groups => "groups",
+            Resource::Invoices => "invoices",
+            Resource::Products => "products",
+            Resource::Sections => "sections",
+            Resource::Tickets => "tickets",
+        };
+    }
+}
+
+/// Builder for the URL parameters of list endpoints, with values URL-encoded.
+///
+/// Date-range filters are sent as ``created_at[from]``, ``created_at[to]``, ``updated_at[from]`` and
+/// ``updated_at[to]``, with the timestamp in RFC 3339 UTC. With the ``chrono`` or ``time`` features,
+/// ``chrono::DateTime`` and ``time::OffsetDateTime`` values can be passed directly.
+///
+/// ```
+/// use sellapp::{resource::ListQuery, timestamp::Timestamp};
+///
+/// let since = Timestamp::parse("2024-08-01T00:00:00Z").unwrap();
+/// let query = ListQuery::new().limit(50).created_from(since);
+/// assert_eq!(query.to_string(), "?limit=50&created_at%5Bfrom%5D=2024-08-01T00%3A00%3A00Z");
+///
+/// // With the paging helpers, which add their own "?limit=..&page=..":
+/// let extra_params = query.to_extra_params();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListQuery {
+    params: Vec<(String, String)>,
+}
+
+impl ListQuery {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Add any URL parameter.
+    pub fn param(mut self, key: &str, value: impl ToString) -> Self {
+        self.params.push((key.to_string(), value.to_string()));
+        return self;
+    }
+
+    /// Amount of items per page.
+    pub fn limit(self, limit: usize) -> Self {
+        return self.param("limit", limit);
+    }
+
+    /// The page to fetch, starting at 1.
+    pub fn page(self, page: u32) -> Self {
+        return self.param("page", page);
+    }
+
+    /// Sort by an attribute, e.g. **"updated_at"**.
+    pub fn sort(self, field: &str, descending: bool) -> Self {
+        let order = if descending { "desc" } else { "asc" };
+        return self.param("sort", field).param("order", order);
+    }
+
+    /// Only include items created at or after ``from``.
+    pub fn created_from(self, from: impl Into<Timestamp>) -> Self {
+        return self.param("created_at[from]", from.into());
+    }
+
+    /// Only include items created at or before ``to``.
+    pub fn created_to(self, to: impl Into<Timestamp>) -> Self {
+        return self.param("created_at[to]", to.into());
+    }
+
+    /// Only include items updated at or after ``from``.
+    pub fn updated_from(self, from: impl Into<Timestamp>) -> Self {
+        return self.param("updated_at[from]", from.into());
+    }
+
+    /// Only include items updated at or before ``to``.
+    pub fn updated_to(self, to: impl Into<Timestamp>) -> Self {
+        return self.param("updated_at[to]", to.into());
+    }
+
+    /// Get the parameters to append after existing ones, e.g. as the ``extra_params`` of ``list_all_pages``.
+    pub fn to_extra_params(&self) -> String {
+        let query = self.encoded();
+        return match query.is_empty() {
+            true => query,
+            false => format!("&{}", query),
+        };
+    }
+
+    fn encoded(&self) -> String {
+        let mut url = Url::parse("https://sell.app/").unwrap();
+        if self.params.is_empty() {
+            return String::new();
+        }
+        url.query_pairs_mut().extend_pairs(&self.params);
+        return url.query().unwrap_or_default().to_string();
+    }
+}
+
+/// Formats as the ``url_params`` of the "list all" endpoints, e.g. **"?limit=50&page=1"**.
+impl fmt::Display for ListQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let query = self.encoded();
+        if query.is_empty() {
+            return Ok(());
+        }
+        return write!(f, "?{}", query);
+    }
+}
+
+impl SellAppClient {
+    /// Call the "list all" endpoint of ``resource``.
+    ///
+    /// ``url_params``: Optional attributes to append to the request URL, e.g. **"?limit=50&page=1"**
+    pub async fn list(&self, resource: Resource, url_params: &str) -> Result<Response, Error> {
+        return match resource {
+            Resource::Blacklists => self.blacklist_list_all_rules(url_params).await,
+            Resource::Coupons => self.coupons_list_all(url_params).await,
+            Resource::Feedback => self.feedback_list_all(url_params).await,
+            Resource::Groups => self.groups_list_all(url_params).await,
+            Resource::Invoices => self.invoices_list_all(url_params).await,
+            Resource::Products => self.products_list_all(url_params).await,
+            Resource::Sections => self.sections_list_all(url_params).await,
+            Resource::Tickets => self.tickets_list_all(url_params).await,
+        };
+    }
+
+    /// Fetch a single page of ``resource``, with ``PAGE_SIZE`` items per page.
+    ///
+    /// ``page``: The page to fetch, starting at 1.
+    ///
+    /// ``extra_params``: Optional attributes to append to the request URL after the paging attributes, e.g. **"&with_trashed=true"**
+    pub async fn list_page(
+        &self,
+        resource: Resource,
+        page: u32,
+        extra_params: &str,
+    ) -> Result<Vec<Value>, Error> {
+        let params = format!("?limit={}&page={}{}", PAGE_SIZE, page, extra_params);
+        let res = self.list(resource, params.as_str()).await?;
+        return page_items(res).await;
+    }
+
+    /// Fetch every item of ``resource`` by requesting pages until the last one.
+    ///
+    /// ``extra_params``: Optional attributes to append to the request URL after the paging attributes, e.g. **"&with_trashed=true"**
+    pub async fn list_all_pages(
+        &self,
+        resource: Resource,
+        extra_params: &str,
+    ) -> Result<Vec<Value>, Error> {
+        let mut items = Vec::new();
+        for page in 1.. {
+            let page_items = self.list_page(resource, page, extra_params).await?;
+            let last_page = page_items.len() < PAGE_SIZE;
+            items.extend(page_items);
+            if last_page {
+                break;
+            }
+        }
+        return Ok(items);
+    }
+
+    /// Fetch every variant of a product by requesting pages until the last one.
+    pub async fn variants_list_all_pages(&self, product_id: String) -> Result<Vec<Value>, Error> {
+        let mut items = Vec::new();
+        for page in 1.. {
+            let params = format!("?limit={}&page={}", PAGE_SIZE, page);
+            let res = self
+                .variants_list_all(product_id.clone(), params.as_str())
+                .await?;
+            let page_items = page_items(res).await?;
+            let last_page = page_items.len() < PAGE_SIZE;
+            items.extend(page_items);
+            if last_page {
+                break;
+            }
+        }
+        return Ok(items);
+    }
+
+    /// Fetch every product of a group by requesting pages until the last one.
+    pub async fn groups_list_products_all_pages(
+        &self,
+        group_id: String,
+    ) -> Result<Vec<Value>, Error> {
+        let mut items = Vec::new();
+        for page in 1.. {
+            let params = format!("?limit={}&page={}", PAGE_SIZE, page);
+            let res = self
+                .groups_list_products(group_id.clone(), params.as_str())
+                .await?;
+            let page_items = page_items(res).await?;
+            let last_page = page_items.len() < PAGE_SIZE;
+            items.extend(page_items);
+            if last_page {
+                break;
+            }
+        }
+        return Ok(items);
+    }
+}
+
+/// Get the items of a list response, failing on error status codes.
+async fn page_items(res: Response) -> Result<Vec<Value>, Error> {
+    let mut body = res.error_for_status()?.json::<Value>().await?;
+    return match body["data"].take() {
+        Value::Array(items) => Ok(items),
+        _ => Ok(Vec::new()),
+    };
+}
@@ -0,0 +1,46 @@
+use crate::resource::Resource;
+
+/// A version of the Sell.App API, which prefixes every route, e.g. **"v2/products"**.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ApiVersion {
+    V1,
+    V2,
+}
+
+impl ApiVersion {
+    /// The route prefix of the version, e.g. **"v2"**.
+    pub fn prefix(&self) -> &'static str {
+        return match self {
+            ApiVersion::V1 => "v1",
+            ApiVersion::V2 => "v2",
+        };
+    }
+}
+
+/// The API versions the endpoints of ``resource`` are available in, with the version used by default first.
+///
+/// Product variants are routed with ``Resource::Products``.
+pub fn supported_versions(resource: Resource) -> &'static [ApiVersion] {
+    return match resource {
+        Resource::Blacklists => &[ApiVersion::V1],
+        Resource::Coupons => &[ApiVersion::V1],
+        Resource::Feedback => &[ApiVersion::V1],
+        Resource::Groups => &[ApiVersion::V2],
+        Resource::Invoices => &[ApiVersion::V2, ApiVersion::V1],
+        Resource::Products => &[ApiVersion::V2, ApiVersion::V1],
+        Resource::Sections => &[ApiVersion::V1],
+        Resource::Tickets => &[ApiVersion::V1],
+    };
+}
+
+/// The API version used for ``resource`` unless the client overrides it.
+pub fn default_version(resource: Resource) -> ApiVersion {
+    return supported_versions(resource)[0];
+}
+
+/// Build the path of a route relative to the API root, e.g. **"v2/products/12/variants"**.
+///
+/// ``rest``: Anything after the resource name, e.g. **"/12/variants"** or URL parameters.
+pub fn route(version: ApiVersion, resource: Resource, rest: &str) -> String {
+    return format!("{}/{}{}", version.prefix(), resource.name(), rest);
+}
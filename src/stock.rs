@@ -0,0 +1,325 @@
+use std::{collections::HashSet, fmt, fs, io, path::Path};
+
+use serde_json::{json, Value};
+
+use crate::{
+    resource::Resource,
+    util::{resource_id, unwrap_data},
+    SellAppClient,
+};
+
+/// Outcome of adding or removing serial keys of a variant.
+#[derive(Debug, Clone, Default)]
+pub struct SerialUpdate {
+    /// Amount of keys that were added or removed.
+    pub changed: usize,
+    /// Keys that were skipped: when adding, keys already in stock or given twice, when removing, keys not in stock.
+    pub skipped: Vec<String>,
+    /// Amount of keys in stock after the update.
+    pub remaining: usize,
+}
+
+/// Serial key stock of a single variant.
+#[derive(Debug, Clone)]
+pub struct VariantStock {
+    pub product_id: String,
+    pub variant_id: String,
+    pub title: Option<String>,
+    /// Amount of serial keys left.
+    pub stock: usize,
+}
+
+/// Error returned while managing serial keys.
+#[derive(Debug)]
+pub enum StockError {
+    /// Calling the API failed.
+    Http(crate::Error),
+    /// Reading a file of keys failed.
+    Io(io::Error),
+}
+
+impl fmt::Display for StockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            StockError::Http(error) => write!(f, "request failed: {}", error),
+            StockError::Io(error) => write!(f, "failed to read keys: {}", error),
+        };
+    }
+}
+
+impl std::error::Error for StockError {}
+
+impl From<crate::Error> for StockError {
+    fn from(error: crate::Error) -> Self {
+        return StockError::Http(error);
+    }
+}
+
+impl From<reqwest::Error> for StockError {
+    fn from(error: reqwest::Error) -> Self {
+        return StockError::Http(error.into());
+    }
+}
+
+impl From<io::Error> for StockError {
+    fn from(error: io::Error) -> Self {
+        return StockError::Io(error);
+    }
+}
+
+impl SellAppClient {
+    /// Get the serial keys in stock for a variant that delivers serials.
+    ///
+    /// ``product_id``: The ID of the product, required.
+    ///
+    /// ``variant_id``: The ID of the variant, required.
+    pub async fn variants_get_serials(
+        &self,
+        product_id: String,
+        variant_id: String,
+    ) -> Result<Vec<String>, StockError> {
+        let variant = self.variant_json(product_id, variant_id).await?;
+        return Ok(serials(&variant).unwrap_or_default());
+    }
+
+    /// Append serial keys to the deliverables of a variant.
+    ///
+    /// Keys that are already in stock, or given more than once, are skipped and reported in ``SerialUpdate::skipped``.
+    /// Surrounding whitespace is trimmed and empty keys are ignored.
+    ///
+    /// **Note**: The API has no way to add keys atomically. The variant is read right before it is written back
+    /// with the new keys, but keys delivered or changed by someone else in between are overwritten, so avoid
+    /// running this while the variant is on sale or edited elsewhere.
+    ///
+    /// ``product_id``: The ID of the product, required.
+    ///
+    /// ``variant_id``: The ID of the variant, required.
+    ///
+    /// ``keys``: The serial keys to add, required.
+    pub async fn variants_add_serials(
+        &self,
+        product_id: String,
+        variant_id: String,
+        keys: impl IntoIterator<Item = String>,
+    ) -> Result<SerialUpdate, StockError> {
+        let keys = keys
+            .into_iter()
+            .map(|key| key.trim().to_string())
+            .filter(|key| !key.is_empty())
+            .collect();
+        return self
+            .variant_update_serials(product_id, variant_id, keys, Vec::new())
+            .await;
+    }
+
+    /// Append serial keys from a file with one key per line, see ``variants_add_serials``.
+    pub async fn variants_add_serials_from_file(
+        &self,
+        product_id: String,
+        variant_id: String,
+        path: impl AsRef<Path>,
+    ) -> Result<SerialUpdate, StockError> {
+        let contents = fs::read_to_string(path)?;
+        let keys = contents.lines().map(str::to_string);
+        return self
+            .variants_add_serials(product_id, variant_id, keys)
+            .await;
+    }
+
+    /// Remove consumed or leaked serial keys from the deliverables of a variant.
+    ///
+    /// Keys that are not in stock are reported in ``SerialUpdate::skipped``.
+    ///
+    /// **Note**: The API has no way to remove keys atomically. The variant is read right before it is written back
+    /// without the keys, but keys delivered or changed by someone else in between are overwritten, so avoid
+    /// running this while the variant is on sale or edited elsewhere.
+    ///
+    /// ``product_id``: The ID of the product, required.
+    ///
+    /// ``variant_id``: The ID of the variant, required.
+    ///
+    /// ``keys``: The serial keys to remove, required.
+    pub async fn variants_remove_serials(
+        &self,
+        product_id: String,
+        variant_id: String,
+        keys: impl IntoIterator<Item = String>,
+    ) -> Result<SerialUpdate, StockError> {
+        let keys = keys.into_iter().map(|key| key.trim().to_string()).collect();
+        return self
+            .variant_update_serials(product_id, variant_id, Vec::new(), keys)
+            .await;
+    }
+
+    /// Get the serial key stock of every variant that delivers serials, across all products.
+    pub async fn variants_stock_report(&self) -> Result<Vec<VariantStock>, StockError> {
+        let client = self.uncached();
+        let mut report = Vec::new();
+        for product in client.list_all_pages(Resource::Products, "").await? {
+            let product_id = resource_id(&product);
+            for variant in client.variants_list_all_pages(product_id.clone()).await? {
+                if let Some(serials) = serials(&variant) {
+                    report.push(VariantStock {
+                        product_id: product_id.clone(),
+                        variant_id: resource_id(&variant),
+                        title: variant["title"].as_str().map(str::to_string),
+                        stock: serials.len(),
+                    });
+                }
+            }
+        }
+        return Ok(report);
+    }
+
+    /// Get every variant that delivers serials and has fewer than ``threshold`` keys left.
+    pub async fn variants_low_stock(
+        &self,
+        threshold: usize,
+    ) -> Result<Vec<VariantStock>, StockError> {
+        let mut report = self.variants_stock_report().await?;
+        report.retain(|variant| variant.stock < threshold);
+        return Ok(report);
+    }
+
+    async fn variant_json(
+        &self,
+        product_id: String,
+        variant_id: String,
+    ) -> Result<Value, StockError> {
+        let res = self
+            .uncached()
+            .variants_get(product_id, variant_id)
+            .await?
+            .error_for_status()?;
+        return Ok(unwrap_data(res.json::<Value>().await?));
+    }
+
+    /// Add and remove serial keys of a variant. The variant is fetched bypassing the response cache right before
+    /// writing, and only ``added`` and ``removed`` are applied to the keys it has at that point.
+    async fn variant_update_serials(
+        &self,
+        product_id: String,
+        variant_id: String,
+        added: Vec<String>,
+        removed: Vec<String>,
+    ) -> Result<SerialUpdate, StockError> {
+        let variant = self
+            .variant_json(product_id.clone(), variant_id.clone())
+            .await?;
+        let mut stock = serials(&variant).unwrap_or_default();
+        let mut seen: HashSet<String> = stock.iter().cloned().collect();
+
+        let mut update = SerialUpdate::default();
+        for key in removed {
+            match stock.iter().position(|serial| *serial == key) {
+                Some(index) => {
+                    stock.remove(index);
+                    update.changed += 1;
+                }
+                None => update.skipped.push(key),
+            }
+        }
+        for key in added {
+            if seen.insert(key.clone()) {
+                stock.push(key);
+                update.changed += 1;
+            } else {
+                update.skipped.push(key);
+            }
+        }
+
+        if update.changed > 0 {
+            self.variant_set_serials(product_id, variant_id, &variant, &stock)
+                .await?;
+        }
+        update.remaining = stock.len();
+        return Ok(update);
+    }
+
+    /// Replace the serial keys of a variant, keeping the rest of its deliverable settings.
+    async fn variant_set_serials(
+        &self,
+        product_id: String,
+        variant_id: String,
+        variant: &Value,
+        stock: &[String],
+    ) -> Result<(), StockError> {
+        let mut deliverable = variant["deliverable"].clone();
+        if !deliverable.is_object() {
+            deliverable = json!({ "types": ["SERIALS"] });
+        }
+        if !deliverable["data"].is_object() {
+            deliverable["data"] = json!({});
+        }
+        deliverable["data"]["serials"] = json!(stock);
+
+        let data = json!({ "deliverable": deliverable });
+        self.variants_update(product_id, variant_id, data.to_string())
+            .await?
+            .error_for_status()?;
+        return Ok(());
+    }
+}
+
+/// Get the serial keys of a variant, or ``None`` if it doesn't deliver serials.
+///
+/// Keys stored as numbers (or any other JSON value) are returned as their JSON text, so they are kept when the
+/// keys are written back. Only ``null`` entries are left out.
+pub(crate) fn serials(variant: &Value) -> Option<Vec<String>> {
+    let serials = variant["deliverable"]["data"]["serials"].as_array()?;
+    return Some(
+        serials
+            .iter()
+            .filter_map(|serial| match serial {
+                Value::String(serial) => Some(serial.clone()),
+                Value::Null => None,
+                serial => Some(serial.to_string()),
+            })
+            .collect(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::{self, MockTransport};
+
+    fn variant(serials: Value) -> Value {
+        return json!({ "id": 2, "deliverable": { "types": ["SERIALS"], "data": { "serials": serials } } });
+    }
+
+    #[test]
+    fn reads_serials_of_any_type() {
+        let variant = variant(json!(["KEY-1", 12345, null, 1.5]));
+        assert_eq!(serials(&variant).unwrap(), ["KEY-1", "12345", "1.5"]);
+        assert_eq!(
+            serials(&json!({ "deliverable": { "types": ["FILE"] } })),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_numeric_serials_when_adding() {
+        let transport = MockTransport::new(|line, _| match line {
+            "GET v2/products/1/variants/2" => {
+                mock::json(200, json!({ "data": variant(json!(["KEY-1", 12345])) }))
+            }
+            _ => mock::json(200, json!({ "data": {} })),
+        });
+        let client = mock::client(&transport);
+        let keys = ["12345".to_string(), "KEY-2".to_string()];
+        let update = client
+            .variants_add_serials("1".to_string(), "2".to_string(), keys)
+            .await
+            .unwrap();
+        assert_eq!((update.changed, update.remaining), (1, 3));
+        assert_eq!(update.skipped, ["12345"]);
+
+        let body = &transport.bodies("PATCH v2/products/1/variants/2")[0];
+        assert_eq!(
+            body["deliverable"]["data"]["serials"],
+            json!(["KEY-1", "12345", "KEY-2"])
+        );
+        assert_eq!(body["deliverable"]["types"], json!(["SERIALS"]));
+    }
+}
@@ -0,0 +1,295 @@
+use std::{collections::HashMap, fmt, path::Path};
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::{
+    resource::{Resource, PAGE_SIZE, SORT_BY_UPDATED_DESC},
+    util::resource_id,
+    SellAppClient,
+};
+
+/// Migrations applied to the mirror database, in order. ``PRAGMA user_version`` holds how many have been applied.
+const MIGRATIONS: &[&str] = &[r#"
+    CREATE TABLE "blacklists" (id TEXT PRIMARY KEY, updated_at TEXT, data TEXT NOT NULL, sync_run INTEGER NOT NULL);
+    CREATE TABLE "coupons" (id TEXT PRIMARY KEY, updated_at TEXT, data TEXT NOT NULL, sync_run INTEGER NOT NULL);
+    CREATE TABLE "feedback" (id TEXT PRIMARY KEY, updated_at TEXT, data TEXT NOT NULL, sync_run INTEGER NOT NULL);
+    CREATE TABLE "groups" (id TEXT PRIMARY KEY, updated_at TEXT, data TEXT NOT NULL, sync_run INTEGER NOT NULL);
+    CREATE TABLE "invoices" (id TEXT PRIMARY KEY, updated_at TEXT, data TEXT NOT NULL, sync_run INTEGER NOT NULL);
+    CREATE TABLE "products" (id TEXT PRIMARY KEY, updated_at TEXT, data TEXT NOT NULL, sync_run INTEGER NOT NULL);
+    CREATE TABLE "sections" (id TEXT PRIMARY KEY, updated_at TEXT, data TEXT NOT NULL, sync_run INTEGER NOT NULL);
+    CREATE TABLE "tickets" (id TEXT PRIMARY KEY, updated_at TEXT, data TEXT NOT NULL, sync_run INTEGER NOT NULL);
+    CREATE TABLE "variants" (
+        id TEXT PRIMARY KEY,
+        product_id TEXT NOT NULL,
+        updated_at TEXT,
+        data TEXT NOT NULL,
+        sync_run INTEGER NOT NULL
+    );
+    CREATE INDEX "variants_product_id" ON "variants" (product_id);
+    CREATE TABLE "sync_state" (
+        resource TEXT PRIMARY KEY,
+        last_updated_at TEXT,
+        last_sync_run INTEGER NOT NULL,
+        synced_at TEXT NOT NULL
+    );
+"#];
+
+/// Version of the mirror database schema created by this version of the crate.
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// Error returned while syncing the mirror.
+#[derive(Debug)]
+pub enum SyncError {
+    /// Fetching data from the API failed.
+    Http(crate::Error),
+    /// Reading or writing the database failed.
+    Database(rusqlite::Error),
+    /// The database was created by a newer version of the crate, with the contained schema version.
+    UnsupportedSchema(u32),
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            SyncError::Http(error) => write!(f, "failed to fetch data: {}", error),
+            SyncError::Database(error) => write!(f, "database error: {}", error),
+            SyncError::UnsupportedSchema(version) => write!(
+                f,
+                "database schema version {} is newer than the supported version {}",
+                version, SCHEMA_VERSION
+            ),
+        };
+    }
+}
+
+impl std::error::Error for SyncError {}
+
+impl From<crate::Error> for SyncError {
+    fn from(error: crate::Error) -> Self {
+        return SyncError::Http(error);
+    }
+}
+
+impl From<reqwest::Error> for SyncError {
+    fn from(error: reqwest::Error) -> Self {
+        return SyncError::Http(error.into());
+    }
+}
+
+impl From<rusqlite::Error> for SyncError {
+    fn from(error: rusqlite::Error) -> Self {
+        return SyncError::Database(error);
+    }
+}
+
+/// Amount of rows written per table during a sync, by table name.
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+    pub written: HashMap<&'static str, usize>,
+}
+
+/// Local SQLite mirror of store data, kept up to date with ``sync``.
+///
+/// Every resource type has its own table (``invoices``, ``products``, ``variants``, ``groups``, ``sections``,
+/// ``coupons``, ``blacklists``, ``tickets`` and ``feedback``), holding the ``id``, ``updated_at`` and the full
+/// JSON ``data`` of each item, so it can be queried with SQLite's JSON functions.
+///
+/// ```no_run
+/// use sellapp::sync::StoreMirror;
+///
+/// # async fn run() -> Result<(), sellapp::sync::SyncError> {
+/// let sellapp_api = sellapp::init("your_api_key", "");
+/// let mut mirror = StoreMirror::open("store.sqlite3")?;
+/// mirror.sync(&sellapp_api).await?;
+///
+/// let completed: i64 = mirror.connection().query_row(
+///     "SELECT COUNT(*) FROM invoices WHERE data ->> '$.status.status.status' = 'COMPLETED'",
+///     [],
+///     |row| row.get(0),
+/// )?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct StoreMirror {
+    conn: Connection,
+}
+
+impl StoreMirror {
+    /// Open (or create) the mirror database at ``path``, migrating it to the current schema.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SyncError> {
+        return Self::from_connection(Connection::open(path)?);
+    }
+
+    /// Use an existing connection as the mirror database, migrating it to the current schema.
+    pub fn from_connection(mut conn: Connection) -> Result<Self, SyncError> {
+        migrate(&mut conn)?;
+        return Ok(StoreMirror { conn });
+    }
+
+    /// The underlying database connection, for running your own queries.
+    pub fn connection(&self) -> &Connection {
+        return &self.conn;
+    }
+
+    /// Bring the mirror up to date.
+    ///
+    /// Resources that were never synced are imported in full. Afterwards, only items updated since the last sync are
+    /// fetched, which requires the API to list them newest-updated first. Variants are fetched for every product that
+    /// was updated.
+    ///
+    /// Incremental syncs can't notice deleted items, run ``full_sync`` now and then to remove them.
+    pub async fn sync(&mut self, client: &SellAppClient) -> Result<SyncReport, SyncError> {
+        return self.run(client, false).await;
+    }
+
+    /// Import every item again, removing items that no longer exist.
+    pub async fn full_sync(&mut self, client: &SellAppClient) -> Result<SyncReport, SyncError> {
+        return self.run(client, true).await;
+    }
+
+    async fn run(&mut self, client: &SellAppClient, full: bool) -> Result<SyncReport, SyncError> {
+        let client = &client.uncached();
+        let sync_run: i64 = self.conn.query_row(
+            r#"SELECT COALESCE(MAX(last_sync_run), 0) + 1 FROM "sync_state""#,
+            [],
+            |row| row.get(0),
+        )?;
+        let mut report = SyncReport::default();
+
+        for resource in Resource::ALL {
+            let table = resource.name();
+            let last_updated_at: Option<Option<String>> = self
+                .conn
+                .query_row(
+                    r#"SELECT last_updated_at FROM "sync_state" WHERE resource = ?1"#,
+                    [table],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            let since = match (full, last_updated_at) {
+                (false, Some(last_updated_at)) => last_updated_at,
+                _ => None,
+            };
+            let extra_params = if since.is_some() {
+                SORT_BY_UPDATED_DESC
+            } else {
+                ""
+            };
+
+            let mut newest = since.clone();
+            let mut updated_products = Vec::new();
+            for page in 1.. {
+                let items = client.list_page(resource, page, extra_params).await?;
+                let count = items.len();
+                let mut reached_synced = false;
+
+                let tx = self.conn.transaction()?;
+                for item in items {
+                    let updated_at = item["updated_at"].as_str().map(str::to_string);
+                    if let (Some(updated_at), Some(since)) = (&updated_at, &since) {
+                        if updated_at < since {
+                            reached_synced = true;
+                            break;
+                        }
+                    }
+                    if updated_at > newest {
+                        newest = updated_at.clone();
+                    }
+                    let id = resource_id(&item);
+                    tx.execute(
+                        &format!(
+                            r#"INSERT OR REPLACE INTO "{}" (id, updated_at, data, sync_run) VALUES (?1, ?2, ?3, ?4)"#,
+                            table
+                        ),
+                        params![id, updated_at, item.to_string(), sync_run],
+                    )?;
+                    *report.written.entry(table).or_default() += 1;
+                    if resource == Resource::Products {
+                        updated_products.push(id);
+                    }
+                }
+                tx.commit()?;
+
+                if reached_synced || count < PAGE_SIZE {
+                    break;
+                }
+            }
+
+            if since.is_none() {
+                self.conn.execute(
+                    &format!(r#"DELETE FROM "{}" WHERE sync_run != ?1"#, table),
+                    [sync_run],
+                )?;
+            }
+            if resource == Resource::Products {
+                self.sync_variants(client, &updated_products, sync_run, &mut report)
+                    .await?;
+                if since.is_none() {
+                    self.conn.execute(
+                        r#"DELETE FROM "variants" WHERE product_id NOT IN (SELECT id FROM "products")"#,
+                        [],
+                    )?;
+                }
+            }
+
+            self.conn.execute(
+                r#"INSERT OR REPLACE INTO "sync_state" (resource, last_updated_at, last_sync_run, synced_at)
+                VALUES (?1, ?2, ?3, datetime('now'))"#,
+                params![table, newest, sync_run],
+            )?;
+        }
+
+        return Ok(report);
+    }
+
+    /// Replace the variants of every product in ``product_ids``.
+    async fn sync_variants(
+        &mut self,
+        client: &SellAppClient,
+        product_ids: &[String],
+        sync_run: i64,
+        report: &mut SyncReport,
+    ) -> Result<(), SyncError> {
+        for product_id in product_ids {
+            let variants = client.variants_list_all_pages(product_id.clone()).await?;
+
+            let tx = self.conn.transaction()?;
+            tx.execute(
+                r#"DELETE FROM "variants" WHERE product_id = ?1"#,
+                [product_id],
+            )?;
+            for variant in variants {
+                tx.execute(
+                    r#"INSERT OR REPLACE INTO "variants" (id, product_id, updated_at, data, sync_run)
+                    VALUES (?1, ?2, ?3, ?4, ?5)"#,
+                    params![
+                        resource_id(&variant),
+                        product_id,
+                        variant["updated_at"].as_str(),
+                        variant.to_string(),
+                        sync_run
+                    ],
+                )?;
+                *report.written.entry("variants").or_default() += 1;
+            }
+            tx.commit()?;
+        }
+        return Ok(());
+    }
+}
+
+/// Apply every migration the database is missing.
+fn migrate(conn: &mut Connection) -> Result<(), SyncError> {
+    let version: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version > SCHEMA_VERSION {
+        return Err(SyncError::UnsupportedSchema(version));
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", index as u32 + 1)?;
+        tx.commit()?;
+    }
+    return Ok(());
+}
@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use reqwest::Response;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::middleware::LocalResponse;
+use crate::{
+    middleware::{Middleware, RequestContext},
+    Error,
+};
+
+/// Middleware recording every API call through the ``metrics`` facade.
+///
+/// Emits the following metrics, labelled by ``operation`` (the client method name), ``method`` and ``status``:
+///
+/// - ``sellapp_requests_total``: counter of all requests answered by the API.
+/// - ``sellapp_request_duration_seconds``: histogram of request latency, including retries and rate limit waits.
+///
+/// ``status`` is the status class of the response (e.g. **"2xx"**, **"4xx"**), or **"error"** if no response was received.
+///
+/// Calls that never reach the API are counted separately, labelled by ``operation`` only:
+///
+/// - ``sellapp_cache_hits_total``: calls answered from the response cache.
+/// - ``sellapp_dry_run_calls_total``: calls blocked by dry-run mode.
+///
+/// ``middleware::Retry`` and ``middleware::RateLimit`` add, labelled by ``operation``:
+///
+/// - ``sellapp_retries_total``: counter of retried attempts.
+/// - ``sellapp_rate_limit_waits_total``: counter of calls that had to wait for the rate limiter.
+/// - ``sellapp_rate_limit_wait_seconds``: histogram of how long those calls waited.
+///
+/// Clients created with ``sellapp::init`` have this middleware installed by default when the ``metrics`` feature is enabled.
+pub struct Metrics;
+
+impl Metrics {
+    fn record(&self, ctx: &RequestContext, status: &'static str) {
+        let labels = [
+            ("operation", ctx.operation.to_string()),
+            ("method", ctx.method.to_string()),
+            ("status", status.to_string()),
+        ];
+
+        metrics::counter!("sellapp_requests_total", &labels).increment(1);
+        metrics::histogram!("sellapp_request_duration_seconds", &labels)
+            .record(ctx.started.elapsed().as_secs_f64());
+    }
+}
+
+impl Middleware for Metrics {
+    fn after_response(&self, ctx: &RequestContext, response: &Response) {
+        #[cfg(not(target_arch = "wasm32"))]
+        match response.extensions().get::<LocalResponse>() {
+            Some(LocalResponse::CacheHit) => {
+                metrics::counter!("sellapp_cache_hits_total", "operation" => ctx.operation)
+                    .increment(1);
+                return;
+            }
+            Some(LocalResponse::DryRun) => {
+                metrics::counter!("sellapp_dry_run_calls_total", "operation" => ctx.operation)
+                    .increment(1);
+                return;
+            }
+            None => {}
+        }
+        self.record(ctx, status_class(response.status().as_u16()));
+    }
+
+    fn on_error(&self, ctx: &RequestContext, _error: &Error) {
+        self.record(ctx, "error");
+    }
+}
+
+/// Count an attempt retried by ``middleware::Retry``.
+pub(crate) fn record_retry(ctx: &RequestContext) {
+    metrics::counter!("sellapp_retries_total", "operation" => ctx.operation).increment(1);
+}
+
+/// Count a call that waited for ``middleware::RateLimit``.
+pub(crate) fn record_rate_limit_wait(ctx: &RequestContext, waited: Duration) {
+    metrics::counter!("sellapp_rate_limit_waits_total", "operation" => ctx.operation).increment(1);
+    metrics::histogram!("sellapp_rate_limit_wait_seconds", "operation" => ctx.operation)
+        .record(waited.as_secs_f64());
+}
+
+fn status_class(status: u16) -> &'static str {
+    return match status {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        _ => "5xx",
+    };
+}
@@ -0,0 +1,443 @@
+use std::{fmt, str::FromStr};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::rt::{SystemTime, UNIX_EPOCH};
+
+/// Seconds since 1970-01-01 of 0000-01-01T00:00:00Z, the earliest supported timestamp.
+const MIN_SECONDS: i64 = -62_167_219_200;
+/// Seconds since 1970-01-01 of 9999-12-31T23:59:59Z, the latest supported timestamp.
+const MAX_SECONDS: i64 = 253_402_300_799;
+
+/// A point in time in UTC, as sent by the API in the ``created_at`` and ``updated_at`` attributes.
+///
+/// Parses RFC 3339 timestamps (e.g. **"2024-08-15T12:00:00.000000Z"** or **"2024-08-15T14:00:00+02:00"**), as well as
+/// **"2024-08-15 12:00:00"** and plain dates, which are taken as UTC. Displays and serializes as RFC 3339 in UTC.
+/// Supports the years 0000 to 9999.
+///
+/// With the ``chrono`` or ``time`` features, converts to and from ``chrono::DateTime`` and ``time::OffsetDateTime``,
+/// e.g. ``invoice.created_at.map(|created_at| created_at.to_chrono())``.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    seconds: i64,
+    nanos: u32,
+}
+
+/// Error returned when parsing an invalid timestamp.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimestampError(pub String);
+
+impl fmt::Display for TimestampError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "invalid timestamp: {:?}", self.0);
+    }
+}
+
+impl std::error::Error for TimestampError {}
+
+impl Timestamp {
+    /// Get the timestamp ``seconds`` and ``nanos`` after 1970-01-01T00:00:00Z, or ``None`` if it's out of range.
+    pub fn from_unix(seconds: i64, nanos: u32) -> Option<Self> {
+        if !(MIN_SECONDS..=MAX_SECONDS).contains(&seconds) || nanos >= 1_000_000_000 {
+            return None;
+        }
+        return Some(Timestamp { seconds, nanos });
+    }
+
+    /// Get the current time.
+    pub fn now() -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        return Timestamp {
+            seconds: now.as_secs() as i64,
+            nanos: now.subsec_nanos(),
+        };
+    }
+
+    /// Seconds since 1970-01-01T00:00:00Z.
+    pub fn unix_seconds(&self) -> i64 {
+        return self.seconds;
+    }
+
+    /// Nanoseconds after ``unix_seconds``.
+    pub fn nanos(&self) -> u32 {
+        return self.nanos;
+    }
+
+    /// The UTC date as year, month (1 to 12) and day (1 to 31).
+    pub fn date(&self) -> (i64, u32, u32) {
+        return civil_from_days(self.seconds.div_euclid(86_400));
+    }
+
+    pub fn parse(value: &str) -> Result<Self, TimestampError> {
+        let invalid = || TimestampError(value.to_string());
+        let value_bytes = value.trim().as_bytes();
+        let number = |range: std::ops::Range<usize>| -> Result<i64, TimestampError> {
+            let digits = value_bytes.get(range).ok_or_else(invalid)?;
+            if !digits.iter().all(u8::is_ascii_digit) {
+                return Err(invalid());
+            }
+            return Ok(digits.iter().fold(0, |n, d| n * 10 + (d - b'0') as i64));
+        };
+
+        let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+        if value_bytes.get(4) != Some(&b'-') || value_bytes.get(7) != Some(&b'-') {
+            return Err(invalid());
+        }
+        if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+            return Err(invalid());
+        }
+        let mut seconds = days_from_civil(year, month, day) * 86_400;
+        let mut nanos = 0;
+
+        let rest = &value_bytes[10..];
+        if !rest.is_empty() {
+            if !matches!(rest[0], b'T' | b't' | b' ')
+                || rest.get(3) != Some(&b':')
+                || rest.get(6) != Some(&b':')
+            {
+                return Err(invalid());
+            }
+            let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+            if hour > 23 || minute > 59 || second > 60 {
+                return Err(invalid());
+            }
+            // Leap seconds are folded into the last second of the minute.
+            seconds += hour * 3600 + minute * 60 + second.min(59);
+
+            let mut rest = &value_bytes[19..];
+            if let Some(fraction) = rest.strip_prefix(b".") {
+                let length = fraction.iter().take_while(|b| b.is_ascii_digit()).count();
+                if length == 0 {
+                    return Err(invalid());
+                }
+                for (index, digit) in fraction[..length.min(9)].iter().enumerate() {
+                    nanos += (digit - b'0') as u32 * 10u32.pow(8 - index as u32);
+                }
+                rest = &fraction[length..];
+            }
+            seconds -= match rest {
+                [] | [b'Z'] | [b'z'] => 0,
+                [sign @ (b'+' | b'-'), h1, h2, b':', m1, m2]
+                | [sign @ (b'+' | b'-'), h1, h2, m1, m2] => {
+                    let digits = [*h1, *h2, *m1, *m2];
+                    if !digits.iter().all(u8::is_ascii_digit) {
+                        return Err(invalid());
+                    }
+                    let hours = ((h1 - b'0') * 10 + (h2 - b'0')) as i64;
+                    let minutes = ((m1 - b'0') * 10 + (m2 - b'0')) as i64;
+                    if hours > 23 || minutes > 59 {
+                        return Err(invalid());
+                    }
+                    let offset = hours * 3600 + minutes * 60;
+                    if *sign == b'-' {
+                        -offset
+                    } else {
+                        offset
+                    }
+                }
+                _ => return Err(invalid()),
+            };
+        }
+
+        return Timestamp::from_unix(seconds, nanos).ok_or_else(invalid);
+    }
+
+    /// Convert to a ``chrono::DateTime`` in UTC.
+    #[cfg(feature = "chrono")]
+    pub fn to_chrono(&self) -> chrono::DateTime<chrono::Utc> {
+        return (*self).into();
+    }
+
+    /// Convert to a ``time::OffsetDateTime`` in UTC.
+    #[cfg(feature = "time")]
+    pub fn to_offset_date_time(&self) -> time::OffsetDateTime {
+        return (*self).into();
+    }
+
+    /// Get the timestamp clamped to the supported range.
+    #[cfg(any(feature = "chrono", feature = "time"))]
+    fn clamped(seconds: i64, nanos: u32) -> Self {
+        if seconds < MIN_SECONDS {
+            return Timestamp {
+                seconds: MIN_SECONDS,
+                nanos: 0,
+            };
+        }
+        if seconds > MAX_SECONDS {
+            return Timestamp {
+                seconds: MAX_SECONDS,
+                nanos: 999_999_999,
+            };
+        }
+        return Timestamp {
+            seconds,
+            nanos: nanos.min(999_999_999),
+        };
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = self.date();
+        let time = self.seconds.rem_euclid(86_400);
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            year,
+            month,
+            day,
+            time / 3600,
+            time / 60 % 60,
+            time % 60
+        )?;
+        if !self.nanos.is_multiple_of(1000) {
+            write!(f, ".{:09}", self.nanos)?;
+        } else if self.nanos != 0 {
+            write!(f, ".{:06}", self.nanos / 1000)?;
+        }
+        return f.write_str("Z");
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = TimestampError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        return Timestamp::parse(value);
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        return serializer.collect_str(self);
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        return Timestamp::parse(&value).map_err(de::Error::custom);
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<Timestamp> for chrono::DateTime<chrono::Utc> {
+    fn from(timestamp: Timestamp) -> Self {
+        return chrono::DateTime::from_timestamp(timestamp.seconds, timestamp.nanos)
+            .expect("timestamp in the range of chrono");
+    }
+}
+
+/// Timestamps outside of the years 0000 to 9999 are clamped.
+#[cfg(feature = "chrono")]
+impl<Tz: chrono::TimeZone> From<chrono::DateTime<Tz>> for Timestamp {
+    fn from(datetime: chrono::DateTime<Tz>) -> Self {
+        return Timestamp::clamped(datetime.timestamp(), datetime.timestamp_subsec_nanos());
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<Timestamp> for time::OffsetDateTime {
+    fn from(timestamp: Timestamp) -> Self {
+        let nanos = timestamp.seconds as i128 * 1_000_000_000 + timestamp.nanos as i128;
+        return time::OffsetDateTime::from_unix_timestamp_nanos(nanos)
+            .expect("timestamp in the range of time");
+    }
+}
+
+/// Timestamps outside of the years 0000 to 9999 are clamped.
+#[cfg(feature = "time")]
+impl From<time::OffsetDateTime> for Timestamp {
+    fn from(datetime: time::OffsetDateTime) -> Self {
+        return Timestamp::clamped(datetime.unix_timestamp(), datetime.nanosecond());
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    return year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    return match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+pub(crate) fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    return era * 146097 + day_of_era - 719468;
+}
+
+/// Year, month and day of a date given as days since 1970-01-01.
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400;
+    return (if month <= 2 { year + 1 } else { year }, month, day);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(value: &str) -> Option<String> {
+        return Timestamp::parse(value).ok().map(|t| t.to_string());
+    }
+
+    #[test]
+    fn parses_rfc3339() {
+        assert_eq!(
+            parse("2024-08-15T12:00:00Z").unwrap(),
+            "2024-08-15T12:00:00Z"
+        );
+        assert_eq!(
+            parse("2024-08-15t12:00:00z").unwrap(),
+            "2024-08-15T12:00:00Z"
+        );
+        assert_eq!(
+            parse("2024-08-15T12:00:00.000000Z").unwrap(),
+            "2024-08-15T12:00:00Z"
+        );
+        assert_eq!(
+            parse("2024-08-15T12:00:00.123456Z").unwrap(),
+            "2024-08-15T12:00:00.123456Z"
+        );
+        assert_eq!(
+            parse("2024-08-15T12:00:00.123456789123Z").unwrap(),
+            "2024-08-15T12:00:00.123456789Z"
+        );
+    }
+
+    #[test]
+    fn parses_offsets() {
+        assert_eq!(
+            parse("2024-08-15T14:00:00+02:00").unwrap(),
+            "2024-08-15T12:00:00Z"
+        );
+        assert_eq!(
+            parse("2024-08-15T14:00:00+0200").unwrap(),
+            "2024-08-15T12:00:00Z"
+        );
+        assert_eq!(
+            parse("2024-08-15T22:30:00-03:30").unwrap(),
+            "2024-08-16T02:00:00Z"
+        );
+        assert_eq!(
+            parse("2024-08-15T12:00:00+23:59").unwrap(),
+            "2024-08-14T12:01:00Z"
+        );
+        assert!(parse("2024-08-15T12:00:00+24:00").is_none());
+        assert!(parse("2024-08-15T12:00:00+99:00").is_none());
+        assert!(parse("2024-08-15T12:00:00+02:60").is_none());
+        assert!(parse("2024-08-15T12:00:00+2:00").is_none());
+        assert!(parse("2024-08-15T12:00:00+0a:00").is_none());
+    }
+
+    #[test]
+    fn parses_dates_and_space_separated_times() {
+        assert_eq!(parse("2024-08-15").unwrap(), "2024-08-15T00:00:00Z");
+        assert_eq!(
+            parse(" 2024-08-15 12:00:00 ").unwrap(),
+            "2024-08-15T12:00:00Z"
+        );
+        assert_eq!(parse("2024-02-29").unwrap(), "2024-02-29T00:00:00Z");
+        assert_eq!(parse("0000-01-01").unwrap(), "0000-01-01T00:00:00Z");
+        assert_eq!(
+            parse("9999-12-31T23:59:59Z").unwrap(),
+            "9999-12-31T23:59:59Z"
+        );
+    }
+
+    #[test]
+    fn folds_leap_seconds() {
+        assert_eq!(
+            parse("2016-12-31T23:59:60Z").unwrap(),
+            "2016-12-31T23:59:59Z"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid() {
+        for value in [
+            "",
+            "2024",
+            "2024-8-15",
+            "2024/08/15",
+            "2023-02-29",
+            "2024-13-01",
+            "2024-00-10",
+            "2024-04-31",
+            "2024-08-15T24:00:00Z",
+            "2024-08-15T12:60:00Z",
+            "2024-08-15T12:00:61Z",
+            "2024-08-15T12:00Z",
+            "2024-08-15T12:00:00.Z",
+            "2024-08-15T12:00:00+02",
+            "2024-08-15T12:00:00 UTC",
+            "0000-01-01T00:00:00+00:01",
+        ] {
+            assert!(parse(value).is_none(), "{:?} should be invalid", value);
+        }
+    }
+
+    #[test]
+    fn converts_unix_time() {
+        let timestamp = Timestamp::parse("1970-01-01T00:00:01.5Z").unwrap();
+        assert_eq!(
+            (timestamp.unix_seconds(), timestamp.nanos()),
+            (1, 500_000_000)
+        );
+        let before_epoch = Timestamp::parse("1969-12-31T23:59:59Z").unwrap();
+        assert_eq!(before_epoch.unix_seconds(), -1);
+        assert_eq!(before_epoch.date(), (1969, 12, 31));
+        assert!(Timestamp::from_unix(MAX_SECONDS + 1, 0).is_none());
+        assert!(Timestamp::from_unix(0, 1_000_000_000).is_none());
+    }
+
+    #[test]
+    fn orders_chronologically() {
+        let earlier = Timestamp::parse("2024-08-15T14:00:00+02:00").unwrap();
+        let later = Timestamp::parse("2024-08-15T12:00:01Z").unwrap();
+        assert!(earlier < later);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn converts_to_chrono() {
+        let timestamp = Timestamp::parse("2024-08-15T12:00:00.25Z").unwrap();
+        let datetime = timestamp.to_chrono();
+        assert_eq!(datetime.to_rfc3339(), "2024-08-15T12:00:00.250+00:00");
+        assert_eq!(Timestamp::from(datetime), timestamp);
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn converts_to_time() {
+        let timestamp = Timestamp::parse("2024-08-15T12:00:00.25Z").unwrap();
+        let datetime = timestamp.to_offset_date_time();
+        assert_eq!(datetime.unix_timestamp(), 1_723_723_200);
+        assert_eq!(Timestamp::from(datetime), timestamp);
+    }
+}
@@ -0,0 +1,249 @@
+use std::{error, fmt};
+
+use reqwest::{Client, Request, Response};
+
+#[cfg(not(target_arch = "wasm32"))]
+use futures::future::BoxFuture;
+#[cfg(target_arch = "wasm32")]
+use futures::future::LocalBoxFuture as BoxFuture;
+
+/// Future returned by ``HttpTransport::execute``. It is ``Send`` on native targets only, as the fetch API of wasm isn't.
+pub type TransportFuture = BoxFuture<'static, Result<Response, TransportError>>;
+
+/// Sends the requests of a ``SellAppClient`` over HTTP.
+///
+/// ``reqwest::Client`` is the default implementation. Add your own with ``SellAppClient::with_transport`` to use
+/// another HTTP stack, or a test double that checks requests and answers with canned responses.
+///
+/// The client holds its transport as a trait object instead of being generic over it, so ``SellAppClient`` stays a
+/// single type that every helper (bulk, backup, watcher, ...) accepts, and the transport can be swapped at runtime,
+/// e.g. for one with a different proxy, without changing any signature.
+///
+/// Requests are fully built when they reach the transport: URL, authentication and store headers, and body.
+/// They convert to an ``http::Request`` with ``http::Request::try_from``, so any stack built on the ``http`` crate
+/// (like hyper) fits in. On native targets, responses can be created from any ``http::Response`` with
+/// ``Response::from``, which also makes canned responses possible. reqwest doesn't support that on wasm, where
+/// transports have to wrap a ``reqwest::Client`` instead.
+/// Failures without a response are reported as a ``TransportError``, which callers get as ``Error::Transport``.
+///
+/// A transport that tags every request and passes it on to reqwest, which works on every target:
+///
+/// ```
+/// use reqwest::{header::HeaderValue, Client, Request};
+/// use sellapp::transport::{HttpTransport, TransportFuture};
+///
+/// struct Tagged(Client);
+///
+/// impl HttpTransport for Tagged {
+///     fn execute(&self, mut request: Request) -> TransportFuture {
+///         assert!(request.headers().contains_key("Authorization"));
+///         request.headers_mut().insert("X-Request-Source", HeaderValue::from_static("my-app"));
+///         return HttpTransport::execute(&self.0, request);
+///     }
+/// }
+///
+/// let sellapp_api = sellapp::init("your_api_key", "").with_transport(Tagged(Client::new()));
+/// ```
+pub trait HttpTransport: Send + Sync {
+    fn execute(&self, request: Request) -> TransportFuture;
+}
+
+impl HttpTransport for Client {
+    fn execute(&self, request: Request) -> TransportFuture {
+        let future = Client::execute(self, request);
+        return Box::pin(async move { future.await.map_err(TransportError::from) });
+    }
+}
+
+/// What kind of failure a ``TransportError`` is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportErrorKind {
+    /// Connecting to the API failed, so the request was never sent.
+    Connect,
+    /// No response was received in time.
+    Timeout,
+    /// Any other failure, e.g. the connection broke while the request was in flight.
+    Other,
+}
+
+/// Failure of an ``HttpTransport`` to send a request or receive its response.
+///
+/// ```
+/// use sellapp::transport::{TransportError, TransportErrorKind};
+///
+/// let error = TransportError::new(TransportErrorKind::Timeout, "no response after 30s");
+/// assert_eq!(error.kind(), TransportErrorKind::Timeout);
+/// ```
+#[derive(Debug)]
+pub struct TransportError {
+    kind: TransportErrorKind,
+    source: Box<dyn error::Error + Send + Sync>,
+}
+
+impl TransportError {
+    pub fn new(
+        kind: TransportErrorKind,
+        source: impl Into<Box<dyn error::Error + Send + Sync>>,
+    ) -> Self {
+        return TransportError {
+            kind,
+            source: source.into(),
+        };
+    }
+
+    pub fn kind(&self) -> TransportErrorKind {
+        return self.kind;
+    }
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self.kind {
+            TransportErrorKind::Connect => write!(f, "failed to connect: {}", self.source),
+            TransportErrorKind::Timeout => write!(f, "request timed out: {}", self.source),
+            TransportErrorKind::Other => write!(f, "request failed: {}", self.source),
+        };
+    }
+}
+
+impl error::Error for TransportError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        return Some(&*self.source);
+    }
+}
+
+impl From<reqwest::Error> for TransportError {
+    fn from(error: reqwest::Error) -> Self {
+        let kind = if error.is_timeout() {
+            TransportErrorKind::Timeout
+        } else if is_connect(&error) {
+            TransportErrorKind::Connect
+        } else {
+            TransportErrorKind::Other
+        };
+        return TransportError::new(kind, error);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn is_connect(error: &reqwest::Error) -> bool {
+    return error.is_connect();
+}
+
+/// reqwest doesn't tell connect errors apart on wasm.
+#[cfg(target_arch = "wasm32")]
+fn is_connect(_: &reqwest::Error) -> bool {
+    return false;
+}
+
+/// Test double that answers requests with a handler and records them.
+#[cfg(test)]
+pub(crate) mod mock {
+    use std::sync::{Arc, Mutex};
+
+    use reqwest::{Request, Response, ResponseBuilderExt};
+    use serde_json::Value;
+
+    use super::{HttpTransport, TransportFuture};
+
+    type Handler = dyn Fn(&str, &str, &Value) -> http::Response<String> + Send + Sync;
+
+    /// Answers each request with ``handler``, called with the request line (e.g. **"GET v2/products?page=1"**, with
+    /// the path relative to the API root) and the JSON body, or ``Value::Null`` without one.
+    #[derive(Clone)]
+    pub(crate) struct MockTransport {
+        handler: Arc<Handler>,
+        requests: Arc<Mutex<Vec<(String, Value)>>>,
+    }
+
+    impl MockTransport {
+        pub(crate) fn new(
+            handler: impl Fn(&str, &Value) -> http::Response<String> + Send + Sync + 'static,
+        ) -> Self {
+            return MockTransport::per_store(move |_, line, body| handler(line, body));
+        }
+
+        /// Like ``new``, but ``handler`` is first called with the store slug of the request, to fake several stores.
+        pub(crate) fn per_store(
+            handler: impl Fn(&str, &str, &Value) -> http::Response<String> + Send + Sync + 'static,
+        ) -> Self {
+            return MockTransport {
+                handler: Arc::new(handler),
+                requests: Arc::new(Mutex::new(Vec::new())),
+            };
+        }
+
+        /// Request lines received so far, in order.
+        pub(crate) fn requests(&self) -> Vec<String> {
+            let requests = self.requests.lock().unwrap();
+            return requests.iter().map(|(line, _)| line.clone()).collect();
+        }
+
+        /// Amount of requests whose line starts with ``prefix``.
+        pub(crate) fn count(&self, prefix: &str) -> usize {
+            let requests = self.requests.lock().unwrap();
+            return requests
+                .iter()
+                .filter(|(line, _)| line.starts_with(prefix))
+                .count();
+        }
+
+        /// Bodies of the requests whose line starts with ``prefix``, in order.
+        pub(crate) fn bodies(&self, prefix: &str) -> Vec<Value> {
+            let requests = self.requests.lock().unwrap();
+            return requests
+                .iter()
+                .filter(|(line, _)| line.starts_with(prefix))
+                .map(|(_, body)| body.clone())
+                .collect();
+        }
+    }
+
+    impl HttpTransport for MockTransport {
+        fn execute(&self, request: Request) -> TransportFuture {
+            let url = request.url().clone();
+            let mut line = format!(
+                "{} {}",
+                request.method(),
+                url.path().trim_start_matches("/api/")
+            );
+            if let Some(query) = url.query() {
+                line.push('?');
+                line.push_str(query);
+            }
+            let body = request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .and_then(|body| serde_json::from_slice(body).ok())
+                .unwrap_or_default();
+            let store = request
+                .headers()
+                .get("X-STORE")
+                .and_then(|store| store.to_str().ok())
+                .unwrap_or_default();
+            let (parts, text) = (self.handler)(store, &line, &body).into_parts();
+            self.requests.lock().unwrap().push((line, body));
+
+            let mut builder = http::Response::builder().status(parts.status).url(url);
+            if let Some(headers) = builder.headers_mut() {
+                headers.extend(parts.headers);
+            }
+            let response = Response::from(builder.body(text).unwrap());
+            return Box::pin(async move { Ok(response) });
+        }
+    }
+
+    /// A JSON response with ``status``.
+    pub(crate) fn json(status: u16, body: Value) -> http::Response<String> {
+        return http::Response::builder()
+            .status(status)
+            .header("Content-Type", "application/json")
+            .body(body.to_string())
+            .unwrap();
+    }
+
+    /// A client of the store **"store"** sending requests through ``transport``.
+    pub(crate) fn client(transport: &MockTransport) -> crate::SellAppClient {
+        return crate::init("test_api_key", "store").with_transport(transport.clone());
+    }
+}
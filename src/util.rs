@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+
+use reqwest::Response;
+use serde_json::Value;
+
+use crate::Error;
+
+/// Attributes that are set by the API and can't be passed when creating a resource.
+const READ_ONLY_FIELDS: [&str; 6] = [
+    "id",
+    "store_id",
+    "product_id",
+    "created_at",
+    "updated_at",
+    "deleted_at",
+];
+
+/// Unwrap the ``data`` envelope of a single resource response, if present.
+pub(crate) fn unwrap_data(mut body: Value) -> Value {
+    return match body.get_mut("data") {
+        Some(data) if data.is_object() => data.take(),
+        _ => body,
+    };
+}
+
+/// Get the ``id`` of a resource as a string, regardless of whether the API returned it as a number or a string.
+pub(crate) fn resource_id(resource: &Value) -> String {
+    return id_string(&resource["id"]).unwrap_or_else(|| resource["id"].to_string());
+}
+
+/// Get an ID, e.g. the ``section_id`` of a product, as a string if it is a string or a number.
+pub(crate) fn id_string(value: &Value) -> Option<String> {
+    return match value {
+        Value::String(id) => Some(id.clone()),
+        Value::Number(id) => Some(id.to_string()),
+        _ => None,
+    };
+}
+
+/// Index items by an attribute that identifies them across stores, e.g. **"slug"**. Items without it are left out.
+///
+/// Returns the key shared by several items as error, as it can't tell which of them is meant.
+pub(crate) fn by_key(items: Vec<Value>, field: &str) -> Result<HashMap<String, Value>, String> {
+    let mut map = HashMap::new();
+    for item in items {
+        if let Some(key) = id_string(&item[field]) {
+            if map.contains_key(&key) {
+                return Err(key);
+            }
+            map.insert(key, item);
+        }
+    }
+    return Ok(map);
+}
+
+/// Copy of a resource without the attributes set by the API, to pass to its create or update method.
+pub(crate) fn creatable(resource: &Value) -> Value {
+    let mut data = resource.clone();
+    if let Some(fields) = data.as_object_mut() {
+        for field in READ_ONLY_FIELDS {
+            fields.remove(field);
+        }
+    }
+    return data;
+}
+
+/// Get the body of a create or update response, or why the call failed, for workflows that report failures per item.
+pub(crate) async fn check_response(res: Result<Response, Error>) -> Result<Value, String> {
+    let res = res.map_err(|error| error.to_string())?;
+    let status = res.status();
+    let body = res.text().await.map_err(|error| error.to_string())?;
+    if !status.is_success() {
+        return Err(format!("API returned {}: {}", status, body));
+    }
+    return Ok(unwrap_data(serde_json::from_str(&body).unwrap_or_default()));
+}
@@ -0,0 +1,536 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt, fs, io,
+    path::PathBuf,
+    sync::Mutex,
+    time::Duration,
+};
+
+use futures::{stream, Stream};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    checkout::invoice_status, resource::SORT_BY_UPDATED_DESC, rt, timestamp::Timestamp,
+    util::resource_id, SellAppClient,
+};
+
+/// Something that happened to an invoice since the last poll.
+#[derive(Debug, Clone)]
+pub enum InvoiceEvent {
+    /// An invoice was created.
+    Created(Value),
+    /// The status of an invoice changed, e.g. to **"COMPLETED"**, **"VOIDED"** or **"REFUNDED"**.
+    ///
+    /// ``from`` is ``None`` if the previous status of the invoice is unknown, e.g. because it is no longer tracked.
+    StatusChanged {
+        invoice: Value,
+        from: Option<String>,
+        to: String,
+    },
+    /// A replacement was issued for an invoice.
+    ReplacementIssued(Value),
+}
+
+/// Last seen state of a single invoice.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InvoiceState {
+    pub status: Option<String>,
+    pub updated_at: Option<Timestamp>,
+    pub replacements: usize,
+}
+
+/// Everything the watcher knows about previously seen invoices.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// ``updated_at`` of the most recently updated invoice seen so far.
+    pub last_updated_at: Option<Timestamp>,
+    /// Last seen state of each invoice, by invoice ID.
+    pub invoices: HashMap<String, InvoiceState>,
+}
+
+/// Storage for the watcher checkpoint, so restarts neither replay nor miss events.
+pub trait CheckpointStore: Send + Sync {
+    /// Load the last saved checkpoint, or ``None`` if the watcher never ran.
+    fn load(&self) -> io::Result<Option<Checkpoint>>;
+
+    fn save(&self, checkpoint: &Checkpoint) -> io::Result<()>;
+}
+
+/// Checkpoint store that keeps the checkpoint in a JSON file.
+pub struct FileCheckpoint {
+    path: PathBuf,
+}
+
+impl FileCheckpoint {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        return FileCheckpoint { path: path.into() };
+    }
+}
+
+impl CheckpointStore for FileCheckpoint {
+    fn load(&self) -> io::Result<Option<Checkpoint>> {
+        let data = match fs::read(&self.path) {
+            Ok(data) => data,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error),
+        };
+        return Ok(Some(serde_json::from_slice(&data)?));
+    }
+
+    fn save(&self, checkpoint: &Checkpoint) -> io::Result<()> {
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_vec(checkpoint)?)?;
+        return fs::rename(tmp_path, &self.path);
+    }
+}
+
+/// Checkpoint store that only keeps the checkpoint in memory, for watchers that don't need to survive restarts.
+#[derive(Default)]
+pub struct MemoryCheckpoint {
+    checkpoint: Mutex<Option<Checkpoint>>,
+}
+
+impl CheckpointStore for MemoryCheckpoint {
+    fn load(&self) -> io::Result<Option<Checkpoint>> {
+        return Ok(self.checkpoint.lock().unwrap().clone());
+    }
+
+    fn save(&self, checkpoint: &Checkpoint) -> io::Result<()> {
+        *self.checkpoint.lock().unwrap() = Some(checkpoint.clone());
+        return Ok(());
+    }
+}
+
+/// Error returned by the watcher stream. The stream keeps polling after an error.
+#[derive(Debug)]
+pub enum WatchError {
+    /// Listing the invoices failed.
+    Http(crate::Error),
+    /// Loading or saving the checkpoint failed.
+    Checkpoint(io::Error),
+    /// More invoices changed since the last poll than ``InvoiceWatcher::with_max_pages`` pages hold, so changes
+    /// to invoices updated after ``from`` and before ``to`` were missed. The events of the invoices updated since
+    /// ``to`` follow.
+    Gap {
+        from: Option<Timestamp>,
+        to: Option<Timestamp>,
+    },
+}
+
+impl fmt::Display for WatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            WatchError::Http(error) => write!(f, "failed to list invoices: {}", error),
+            WatchError::Checkpoint(error) => write!(f, "failed to access checkpoint: {}", error),
+            WatchError::Gap { from, to } => write!(
+                f,
+                "missed invoices updated between {} and {}",
+                from.map_or("the start".to_string(), |from| from.to_string()),
+                to.map_or("now".to_string(), |to| to.to_string())
+            ),
+        };
+    }
+}
+
+impl std::error::Error for WatchError {}
+
+impl From<crate::Error> for WatchError {
+    fn from(error: crate::Error) -> Self {
+        return WatchError::Http(error);
+    }
+}
+
+impl From<reqwest::Error> for WatchError {
+    fn from(error: reqwest::Error) -> Self {
+        return WatchError::Http(error.into());
+    }
+}
+
+impl From<io::Error> for WatchError {
+    fn from(error: io::Error) -> Self {
+        return WatchError::Checkpoint(error);
+    }
+}
+
+/// Polls ``invoices_list_all`` and turns changes into ``InvoiceEvent``s, for stores that can't receive webhooks.
+///
+/// Created with ``SellAppClient::invoice_watcher``. Invoices are listed newest-updated first, and paging stops
+/// once an invoice older than the checkpoint is reached. If that takes more than ``with_max_pages`` pages,
+/// the stream yields a ``WatchError::Gap`` for the invoices it could not fetch.
+///
+/// On the very first run (when the store has no checkpoint) the current invoices are recorded without emitting
+/// events. The checkpoint is saved only after all events of a poll were taken from the stream, so events are
+/// delivered at least once. If saving fails, the stream yields the error and retries after the poll interval.
+///
+/// ``created_at`` and ``updated_at`` are compared as ``Timestamp``s, so any UTC offset the API uses works.
+pub struct InvoiceWatcher<'a> {
+    client: &'a SellAppClient,
+    store: Box<dyn CheckpointStore>,
+    interval: Duration,
+    page_size: u32,
+    max_pages: u32,
+    max_tracked: usize,
+    sort_params: String,
+}
+
+struct WatchState<'a> {
+    watcher: InvoiceWatcher<'a>,
+    checkpoint: Option<Checkpoint>,
+    pending: VecDeque<Result<InvoiceEvent, WatchError>>,
+    first_poll: bool,
+    /// Whether saving the checkpoint failed last time, so the next attempt waits for the poll interval.
+    save_failed: bool,
+}
+
+impl SellAppClient {
+    /// Create an ``InvoiceWatcher`` that persists its checkpoint in ``store``.
+    ///
+    /// ```no_run
+    /// use futures::StreamExt;
+    /// use sellapp::watcher::{FileCheckpoint, InvoiceEvent};
+    ///
+    /// # async fn run() {
+    /// let sellapp_api = sellapp::init("your_api_key", "");
+    /// let events = sellapp_api
+    ///     .invoice_watcher(FileCheckpoint::new("invoices.checkpoint.json"))
+    ///     .into_stream();
+    /// futures::pin_mut!(events);
+    ///
+    /// while let Some(event) = events.next().await {
+    ///     if let Ok(InvoiceEvent::StatusChanged { invoice, to, .. }) = event {
+    ///         println!("Invoice {} is now {}", invoice["id"], to);
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn invoice_watcher(&self, store: impl CheckpointStore + 'static) -> InvoiceWatcher<'_> {
+        return InvoiceWatcher {
+            client: self,
+            store: Box::new(store),
+            interval: Duration::from_secs(60),
+            page_size: 50,
+            max_pages: 20,
+            max_tracked: 10_000,
+            sort_params: SORT_BY_UPDATED_DESC.to_string(),
+        };
+    }
+}
+
+impl<'a> InvoiceWatcher<'a> {
+    /// How long to wait between polls, defaults to 60 seconds.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        return self;
+    }
+
+    /// Maximum amount of pages fetched per poll, defaults to 20 pages of 50 invoices.
+    pub fn with_max_pages(mut self, max_pages: u32) -> Self {
+        self.max_pages = max_pages;
+        return self;
+    }
+
+    /// Maximum amount of invoices whose state is kept in the checkpoint, defaults to 10000.
+    ///
+    /// The least recently updated invoices are dropped first. Status changes of dropped invoices are reported
+    /// as ``InvoiceEvent::StatusChanged`` with ``from`` set to ``None``.
+    pub fn with_max_tracked(mut self, max_tracked: usize) -> Self {
+        self.max_tracked = max_tracked;
+        return self;
+    }
+
+    /// URL parameters appended to every list request to sort invoices by update time, newest first.
+    ///
+    /// Defaults to ``resource::SORT_BY_UPDATED_DESC``.
+    pub fn with_sort_params(mut self, sort_params: &str) -> Self {
+        self.sort_params = sort_params.to_string();
+        return self;
+    }
+
+    /// Start watching, the returned stream never ends.
+    pub fn into_stream(self) -> impl Stream<Item = Result<InvoiceEvent, WatchError>> + 'a {
+        let state = WatchState {
+            watcher: self,
+            checkpoint: None,
+            pending: VecDeque::new(),
+            first_poll: true,
+            save_failed: false,
+        };
+        return stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(event) = state.pending.pop_front() {
+                    return Some((event, state));
+                }
+                if let Some(checkpoint) = state.checkpoint.take() {
+                    if state.save_failed {
+                        rt::sleep(state.watcher.interval).await;
+                    }
+                    if let Err(error) = state.watcher.store.save(&checkpoint) {
+                        state.checkpoint = Some(checkpoint);
+                        state.save_failed = true;
+                        return Some((Err(error.into()), state));
+                    }
+                    state.save_failed = false;
+                }
+
+                if !state.first_poll {
+                    rt::sleep(state.watcher.interval).await;
+                }
+                state.first_poll = false;
+
+                match state.watcher.poll().await {
+                    Ok((checkpoint, events)) => {
+                        state.checkpoint = Some(checkpoint);
+                        state.pending = events;
+                    }
+                    Err(error) => return Some((Err(error), state)),
+                }
+            }
+        });
+    }
+
+    /// Fetch all invoices updated since the saved checkpoint, and compute the resulting events and checkpoint.
+    async fn poll(
+        &self,
+    ) -> Result<(Checkpoint, VecDeque<Result<InvoiceEvent, WatchError>>), WatchError> {
+        let saved = self.store.load()?;
+        let emit = saved.is_some();
+        let mut checkpoint = saved.unwrap_or_default();
+        let since = checkpoint.last_updated_at;
+        let client = self.client.uncached();
+
+        let mut updated = Vec::new();
+        // Whether paging reached the checkpoint or the last invoice, instead of stopping at ``max_pages``.
+        let mut complete = false;
+        'pages: for page in 1..=self.max_pages {
+            let params = format!(
+                "?limit={}&page={}{}",
+                self.page_size, page, self.sort_params
+            );
+            let body = client
+                .invoices_list_all(params.as_str())
+                .await?
+                .error_for_status()?
+                .json::<Value>()
+                .await?;
+            let invoices = match body["data"].as_array() {
+                Some(invoices) => invoices.clone(),
+                None => {
+                    complete = true;
+                    break;
+                }
+            };
+            let count = invoices.len();
+
+            for invoice in invoices {
+                let seen = match (timestamp(&invoice, "updated_at"), since) {
+                    (Some(updated_at), Some(since)) => updated_at < since,
+                    _ => false,
+                };
+                if seen {
+                    complete = true;
+                    break 'pages;
+                }
+                updated.push(invoice);
+            }
+            if count < self.page_size as usize {
+                complete = true;
+                break;
+            }
+        }
+
+        let mut events = VecDeque::new();
+        if emit && !complete && since.is_some() {
+            events.push_back(Err(WatchError::Gap {
+                from: since,
+                to: updated
+                    .last()
+                    .and_then(|invoice| timestamp(invoice, "updated_at")),
+            }));
+        }
+
+        // Oldest first, so events are emitted in the order they happened.
+        updated.reverse();
+        for invoice in updated {
+            let id = resource_id(&invoice);
+            let state = InvoiceState {
+                status: invoice_status(&invoice),
+                updated_at: timestamp(&invoice, "updated_at"),
+                replacements: invoice["replacements"].as_array().map_or(0, Vec::len),
+            };
+
+            if state.updated_at.is_some() && checkpoint.last_updated_at < state.updated_at {
+                checkpoint.last_updated_at = state.updated_at;
+            }
+
+            let previous = checkpoint.invoices.insert(id, state.clone());
+            if !emit {
+                continue;
+            }
+            let previous = match previous {
+                Some(previous) => previous,
+                None => {
+                    // Invoices created before the previous poll were seen before, but are no longer tracked.
+                    let known = match (timestamp(&invoice, "created_at"), since) {
+                        (Some(created_at), Some(since)) => created_at <= since,
+                        _ => false,
+                    };
+                    match (known, state.status) {
+                        (true, Some(status)) => events.push_back(Ok(InvoiceEvent::StatusChanged {
+                            invoice,
+                            from: None,
+                            to: status,
+                        })),
+                        (true, None) => {}
+                        (false, _) => events.push_back(Ok(InvoiceEvent::Created(invoice))),
+                    }
+                    continue;
+                }
+            };
+            if state.replacements > previous.replacements {
+                events.push_back(Ok(InvoiceEvent::ReplacementIssued(invoice.clone())));
+            }
+            if let Some(status) = &state.status {
+                if previous.status.as_ref() != Some(status) {
+                    events.push_back(Ok(InvoiceEvent::StatusChanged {
+                        invoice,
+                        from: previous.status,
+                        to: status.clone(),
+                    }));
+                }
+            }
+        }
+
+        if checkpoint.invoices.len() > self.max_tracked {
+            let mut tracked: Vec<(String, Option<Timestamp>)> = checkpoint
+                .invoices
+                .iter()
+                .map(|(id, state)| (id.clone(), state.updated_at))
+                .collect();
+            tracked.sort_by_key(|(_, updated_at)| *updated_at);
+            let excess = tracked.len() - self.max_tracked;
+            for (id, _) in tracked.into_iter().take(excess) {
+                checkpoint.invoices.remove(&id);
+            }
+        }
+
+        return Ok((checkpoint, events));
+    }
+}
+
+/// Get a timestamp attribute of an invoice, or ``None`` if it's missing or invalid.
+fn timestamp(invoice: &Value, field: &str) -> Option<Timestamp> {
+    return invoice[field]
+        .as_str()
+        .and_then(|value| Timestamp::parse(value).ok());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use futures::StreamExt;
+    use serde_json::json;
+
+    use super::*;
+    use crate::transport::mock::{self, MockTransport};
+
+    struct FailingCheckpoint {
+        saves: AtomicUsize,
+    }
+
+    impl CheckpointStore for FailingCheckpoint {
+        fn load(&self) -> io::Result<Option<Checkpoint>> {
+            return Ok(None);
+        }
+
+        fn save(&self, _: &Checkpoint) -> io::Result<()> {
+            self.saves.fetch_add(1, Ordering::SeqCst);
+            return Err(io::Error::other("disk full"));
+        }
+    }
+
+    fn at(value: &str) -> Option<Timestamp> {
+        return Some(Timestamp::parse(value).unwrap());
+    }
+
+    fn state(status: &str, updated_at: &str) -> InvoiceState {
+        return InvoiceState {
+            status: Some(status.to_string()),
+            updated_at: at(updated_at),
+            replacements: 0,
+        };
+    }
+
+    #[tokio::test]
+    async fn compares_timestamps_across_offsets() {
+        let transport = MockTransport::new(|_, _| {
+            return mock::json(
+                200,
+                json!({ "data": [
+                    { "id": 2, "status": "COMPLETED", "updated_at": "2026-03-01T12:30:00+00:00" },
+                    // 11:30 UTC, before the checkpoint even though the string sorts after it.
+                    { "id": 1, "status": "COMPLETED", "updated_at": "2026-03-01T13:30:00+02:00" },
+                ] }),
+            );
+        });
+        let client = mock::client(&transport);
+        let store = MemoryCheckpoint::default();
+        let mut checkpoint = Checkpoint {
+            last_updated_at: at("2026-03-01T12:00:00Z"),
+            ..Default::default()
+        };
+        checkpoint
+            .invoices
+            .insert("1".to_string(), state("PENDING", "2026-03-01T11:30:00Z"));
+        checkpoint
+            .invoices
+            .insert("2".to_string(), state("PENDING", "2026-03-01T11:45:00Z"));
+        store.save(&checkpoint).unwrap();
+
+        let (checkpoint, events) = client.invoice_watcher(store).poll().await.unwrap();
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            Ok(InvoiceEvent::StatusChanged { invoice, from, to }) => {
+                assert_eq!(invoice["id"], 2);
+                assert_eq!(from.as_deref(), Some("PENDING"));
+                assert_eq!(to, "COMPLETED");
+            }
+            event => panic!("unexpected event: {:?}", event),
+        }
+        assert_eq!(checkpoint.last_updated_at, at("2026-03-01T12:30:00Z"));
+        assert_eq!(checkpoint.invoices["1"].status.as_deref(), Some("PENDING"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_after_failed_saves() {
+        let transport = MockTransport::new(|_, _| mock::json(200, json!({ "data": [] })));
+        let client = mock::client(&transport);
+        let interval = Duration::from_secs(60);
+        let store = FailingCheckpoint {
+            saves: AtomicUsize::new(0),
+        };
+        let events = client
+            .invoice_watcher(store)
+            .with_interval(interval)
+            .into_stream();
+        futures::pin_mut!(events);
+
+        let started = tokio::time::Instant::now();
+        assert!(matches!(
+            events.next().await,
+            Some(Err(WatchError::Checkpoint(_)))
+        ));
+        assert_eq!(started.elapsed(), Duration::ZERO);
+        assert!(matches!(
+            events.next().await,
+            Some(Err(WatchError::Checkpoint(_)))
+        ));
+        assert!(matches!(
+            events.next().await,
+            Some(Err(WatchError::Checkpoint(_)))
+        ));
+        assert_eq!(started.elapsed(), interval * 2);
+        assert_eq!(transport.count("GET v2/invoices"), 1);
+    }
+}